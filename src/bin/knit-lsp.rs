@@ -0,0 +1,23 @@
+//! `knit-lsp`, a language server for pattern files.
+//!
+//! It's built with the `lsp` feature and talks to the editor over standard input and output.
+
+use std::io;
+use std::process::ExitCode;
+
+use knitting_parse::LanguageServer;
+
+fn main() -> ExitCode {
+    let mut server = LanguageServer::new();
+    if let Err(error) = server.run(io::stdin().lock(), io::stdout().lock()) {
+        eprintln!("error: {}", error);
+        return ExitCode::FAILURE;
+    }
+
+    // The protocol asks for a failing exit code when the editor exits without shutting down first
+    if server.is_shut_down() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
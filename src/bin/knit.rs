@@ -0,0 +1,439 @@
+//! `knit`, a command line tool for pattern files.
+//!
+//! It's built with the `cli` feature, run `knit help` to see the commands.
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::process::ExitCode;
+
+use knitting_parse::{format_pattern, Options, ParseError, ParseErrorType, Pattern, Stitch, YarnFactors};
+
+const USAGE: &str = "Usage: knit <command> <file> [options]
+
+Commands:
+  validate <file>              Check the pattern and report any errors
+  render <file> [--to FORMAT]  Draw the chart as text, svg or html, or a picture of the fabric with fabric (default text)
+  convert <file> --to FORMAT   Write the pattern as text, json, knitml, csv, tsv, pbm, png or punchcard
+  stats <file>                 Print stitch counts, size and yarn needed
+  lint <file>                  Print warnings about things that are usually mistakes
+  fmt <file> [--check]         Rewrite a text pattern in the canonical layout, or only check that it is
+  help                         Show this message
+
+Options:
+  --from FORMAT   Read the file as text, json, knitml, csv or tsv instead of going by its extension
+  --size SIZE     Use one of the sizes from the pattern's sizes option
+  -o FILE         Write to FILE instead of standard output
+  --check         With fmt, report if the file isn't formatted instead of writing it
+
+Use - as the file to read standard input.
+
+Exit codes:
+  0  Success
+  1  The pattern is not valid, can't be written in the asked for format or isn't formatted
+  2  The command line was not valid
+  3  A file couldn't be read or written
+";
+
+/// Why the command failed, holding the message to print.
+enum Failure {
+    Invalid(String),
+    Usage(String),
+    Io(String),
+}
+
+impl Failure {
+    fn exit_code(&self) -> u8 {
+        match self {
+            Failure::Invalid(_) => 1,
+            Failure::Usage(_) => 2,
+            Failure::Io(_) => 3,
+        }
+    }
+
+    fn message(&self) -> &str {
+        match self {
+            Failure::Invalid(message) | Failure::Usage(message) | Failure::Io(message) => message,
+        }
+    }
+}
+
+/// The formats files can be read from or written to.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+enum Format {
+    Text,
+    Json,
+    KnitMl,
+    Csv,
+    Tsv,
+    Svg,
+    Html,
+    Fabric,
+    Pbm,
+    Png,
+    Punchcard,
+}
+
+impl Format {
+    fn parse(name: &str) -> Result<Format, Failure> {
+        match name {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "knitml" | "xml" => Ok(Format::KnitMl),
+            "csv" => Ok(Format::Csv),
+            "tsv" => Ok(Format::Tsv),
+            "svg" => Ok(Format::Svg),
+            "html" => Ok(Format::Html),
+            "fabric" => Ok(Format::Fabric),
+            "pbm" => Ok(Format::Pbm),
+            "png" => Ok(Format::Png),
+            "punchcard" => Ok(Format::Punchcard),
+            _ => Err(Failure::Usage(format!("unknown format `{}`", name))),
+        }
+    }
+
+    /// Picks the format to read a file as from its extension, anything unknown is read as text.
+    fn from_path(path: &str) -> Format {
+        let extension = path.rsplit_once('.').map(|(_, extension)| extension.to_ascii_lowercase()).unwrap_or_default();
+        Format::parse(&extension).unwrap_or(Format::Text)
+    }
+}
+
+/// The command line, once read.
+struct Arguments {
+    command: String,
+    file: String,
+    from: Option<Format>,
+    to: Option<Format>,
+    size: Option<String>,
+    output: Option<String>,
+    check: bool,
+}
+
+fn parse_arguments(mut args: impl Iterator<Item = String>) -> Result<Arguments, Failure> {
+    let command = args.next().ok_or_else(|| Failure::Usage("missing command".to_string()))?;
+    let mut arguments = Arguments {
+        command,
+        file: String::new(),
+        from: None,
+        to: None,
+        size: None,
+        output: None,
+        check: false,
+    };
+    match arguments.command.as_str() {
+        "help" | "--help" | "-h" => return Ok(arguments),
+        "validate" | "render" | "convert" | "stats" | "lint" | "fmt" => {}
+        command => return Err(Failure::Usage(format!("unknown command `{}`", command))),
+    }
+
+    let mut file = None;
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| Failure::Usage(format!("`{}` needs a value", arg)));
+        match arg.as_str() {
+            "--from" => arguments.from = Some(Format::parse(&value()?)?),
+            "--to" => arguments.to = Some(Format::parse(&value()?)?),
+            "--size" => arguments.size = Some(value()?),
+            "-o" | "--output" => arguments.output = Some(value()?),
+            "--check" => arguments.check = true,
+            _ if arg.starts_with("--") => return Err(Failure::Usage(format!("unknown option `{}`", arg))),
+            _ if file.is_none() => file = Some(arg),
+            _ => return Err(Failure::Usage(format!("unexpected argument `{}`", arg))),
+        }
+    }
+
+    arguments.file = file.ok_or_else(|| Failure::Usage("missing file".to_string()))?;
+    Ok(arguments)
+}
+
+/// Where an error's line number points.
+enum Location<'a> {
+    /// A line of the source, which is shown under the message.
+    Line(usize, &'a str),
+
+    /// A row of the pattern, counted from 0.
+    Row(usize),
+
+    /// Nowhere in particular.
+    Nowhere,
+}
+
+/// Formats an error the way a compiler would, pointing at the line it's on.
+fn diagnostic(file: &str, error: &ParseError, location: Location) -> Failure {
+    let mut message = format!("error: {}\n", error.error_type().describe());
+    match location {
+        Location::Line(line_number, source) => {
+            let range = match *error.error_type() {
+                ParseErrorType::InvalidSyntaxRange(start, end) => Some((start, end)),
+                _ => None,
+            };
+            message.push_str(&format!(" --> {}:{}", file, line_number));
+            if let Some((start, _)) = range {
+                message.push_str(&format!(":{}", start + 1));
+            }
+            message.push('\n');
+
+            if let Some(line) = source.lines().nth(line_number.wrapping_sub(1)) {
+                let gutter = " ".repeat(line_number.to_string().len());
+                message.push_str(&format!("{} |\n{} | {}\n", gutter, line_number, line));
+                if let Some((start, end)) = range {
+                    let prefix: String = line.chars().take(start).map(|c| if c == '\t' { '\t' } else { ' ' }).collect();
+                    message.push_str(&format!("{} | {}{}\n", gutter, prefix, "^".repeat(end.saturating_sub(start) + 1)));
+                }
+            }
+        }
+        Location::Row(row) => message.push_str(&format!(" --> {}: row {}\n", file, row + 1)),
+        Location::Nowhere => message.push_str(&format!(" --> {}\n", file)),
+    }
+    Failure::Invalid(message)
+}
+
+fn read_input(file: &str) -> Result<Vec<u8>, Failure> {
+    let mut bytes = Vec::new();
+    let result = if file == "-" {
+        io::stdin().read_to_end(&mut bytes).map(|_| ())
+    } else {
+        fs::File::open(file).and_then(|mut opened| opened.read_to_end(&mut bytes)).map(|_| ())
+    };
+    result.map_err(|error| Failure::Io(format!("error: couldn't read {}: {}\n", file, error)))?;
+    Ok(bytes)
+}
+
+fn write_output(output: &Option<String>, bytes: &[u8]) -> Result<(), Failure> {
+    let result = match output {
+        Some(path) => fs::write(path, bytes),
+        None => io::stdout().write_all(bytes),
+    };
+    result.map_err(|error| Failure::Io(format!("error: couldn't write {}: {}\n", output.as_deref().unwrap_or("output"), error)))
+}
+
+/// Formats an error from reading a text pattern.
+fn text_diagnostic(file: &str, error: &ParseError, source: &str) -> Failure {
+    // Rows that can't be padded are reported by row, find the line the row came from.
+    let line_number = match error.error_type() {
+        ParseErrorType::InvalidStitchCount(_) => (1..)
+            .zip(source.lines())
+            .filter(|(_, line)| !line.starts_with('#'))
+            .nth(error.line_number())
+            .map_or(0, |(line_number, _)| line_number),
+        _ => error.line_number(),
+    };
+    diagnostic(file, error, Location::Line(line_number, source))
+}
+
+/// Reads the pattern the command works on.
+fn load(arguments: &Arguments) -> Result<Pattern, Failure> {
+    let bytes = read_input(&arguments.file)?;
+    let source = String::from_utf8_lossy(&bytes);
+    let file = &arguments.file;
+    let format = arguments.from.unwrap_or_else(|| Format::from_path(file));
+
+    let pattern = match format {
+        Format::Text => Pattern::new(&bytes[..]).map_err(|error| text_diagnostic(file, &error, &source))?,
+        Format::Json => serde_json::from_slice(&bytes)
+            .map_err(|error| Failure::Invalid(format!("error: {}\n --> {}:{}:{}\n", error, file, error.line(), error.column())))?,
+        Format::KnitMl => Pattern::from_knitml(&bytes[..]).map_err(|error| {
+            let location = match error.error_type() {
+                ParseErrorType::InvalidStitchCount(_) => Location::Row(error.line_number()),
+                _ => Location::Line(error.line_number(), &source),
+            };
+            diagnostic(file, &error, location)
+        })?,
+        Format::Csv | Format::Tsv => {
+            let delimiter = if format == Format::Csv { ',' } else { '\t' };
+            Pattern::from_csv(&bytes[..], delimiter, Options::default())
+                .map_err(|error| diagnostic(file, &error, Location::Line(error.line_number(), &source)))?
+        }
+        _ => return Err(Failure::Usage(format!("patterns can't be read from {:?} files", format))),
+    };
+
+    match &arguments.size {
+        Some(size) => pattern.for_size(size).map_err(|error| diagnostic(file, &error, Location::Nowhere)),
+        None => Ok(pattern),
+    }
+}
+
+fn validate(arguments: &Arguments) -> Result<(), Failure> {
+    let pattern = load(arguments)?;
+    let report = format!(
+        "{}: ok, {} rows, {} stitches wide\n",
+        arguments.file,
+        pattern.lines().len(),
+        pattern.pattern_width()
+    );
+    write_output(&arguments.output, report.as_bytes())
+}
+
+fn render(arguments: &Arguments) -> Result<(), Failure> {
+    let pattern = load(arguments)?;
+    let chart = match arguments.to.unwrap_or(Format::Text) {
+        Format::Text => pattern.render_text(),
+        Format::Svg => pattern.render_svg(),
+        Format::Html => pattern.render_html(),
+        Format::Fabric => pattern.render_fabric(),
+        format => return Err(Failure::Usage(format!("charts can't be drawn as {:?}, use `knit convert`", format))),
+    };
+    write_output(&arguments.output, chart.as_bytes())
+}
+
+fn convert(arguments: &Arguments) -> Result<(), Failure> {
+    let to = arguments.to.ok_or_else(|| Failure::Usage("convert needs --to".to_string()))?;
+    let pattern = load(arguments)?;
+    let file = &arguments.file;
+    let row_error = |error: ParseError| diagnostic(file, &error, Location::Row(error.line_number()));
+
+    let bytes = match to {
+        Format::Text => pattern.to_text().into_bytes(),
+        Format::Json => {
+            // This unwrap should be fine, patterns are always valid JSON values
+            let mut json = serde_json::to_string_pretty(&pattern).unwrap();
+            json.push('\n');
+            json.into_bytes()
+        }
+        Format::KnitMl => pattern.to_knitml().map_err(row_error)?.into_bytes(),
+        Format::Csv => pattern.to_csv(',').into_bytes(),
+        Format::Tsv => pattern.to_csv('\t').into_bytes(),
+        Format::Pbm => pattern.machine_chart().map_err(row_error)?.to_pbm().into_bytes(),
+        Format::Png => pattern
+            .machine_chart()
+            .map_err(row_error)?
+            .to_png()
+            .map_err(|error| diagnostic(file, &error, Location::Nowhere))?,
+        Format::Punchcard => pattern
+            .machine_chart()
+            .map_err(row_error)?
+            .to_punchcard()
+            .map_err(|error| diagnostic(file, &error, Location::Nowhere))?
+            .into_bytes(),
+        Format::Svg | Format::Html | Format::Fabric => return Err(Failure::Usage(format!("use `knit render --to {:?}` to draw the chart", to).to_lowercase())),
+    };
+    write_output(&arguments.output, &bytes)
+}
+
+fn stats(arguments: &Arguments) -> Result<(), Failure> {
+    let pattern = load(arguments)?;
+    let statistics = pattern.statistics();
+
+    let mut report = String::new();
+    if let Some(title) = &pattern.options().title {
+        report.push_str(&format!("{}\n", title));
+    }
+    if let Some(size) = pattern.size() {
+        report.push_str(&format!("Size: {}\n", size));
+    }
+    report.push_str(&format!("Rows: {}\n", statistics.total_rows()));
+    report.push_str(&format!("Width: {} stitches\n", pattern.pattern_width()));
+    report.push_str(&format!("Stitches: {}\n", statistics.total_stitches()));
+    for stitch in Stitch::ALL {
+        let count = statistics.count(stitch);
+        if count > 0 && stitch != Stitch::NoStitch {
+            report.push_str(&format!("  {}: {}\n", stitch, count));
+        }
+    }
+    if let Some(dimensions) = pattern.finished_dimensions() {
+        report.push_str(&format!(
+            "Finished size: {:.1} x {:.1} cm ({:.1} x {:.1} in)\n",
+            dimensions.width_cm,
+            dimensions.height_cm,
+            dimensions.width_in(),
+            dimensions.height_in()
+        ));
+    }
+    if let Some(yarn) = pattern.estimate_yarn(&YarnFactors::default()) {
+        report.push_str(&format!("Yarn: {:.1} m ({:.1} yd)\n", yarn.meters, yarn.yards()));
+    }
+    write_output(&arguments.output, report.as_bytes())
+}
+
+fn lint(arguments: &Arguments) -> Result<(), Failure> {
+    let pattern = load(arguments)?;
+    let mut report = String::new();
+    for warning in pattern.lint() {
+        report.push_str(&format!(
+            "warning[{}]: {}\n --> {}: row {}, column {}\n",
+            warning.lint.code(),
+            warning.message,
+            arguments.file,
+            warning.row + pattern.first_line_number(),
+            warning.column + 1
+        ));
+    }
+    write_output(&arguments.output, report.as_bytes())
+}
+
+fn fmt(arguments: &Arguments) -> Result<(), Failure> {
+    let bytes = read_input(&arguments.file)?;
+    let source = String::from_utf8_lossy(&bytes);
+    let file = &arguments.file;
+    let formatted = format_pattern(&source).map_err(|error| text_diagnostic(file, &error, &source))?;
+
+    if !arguments.check {
+        write_output(&arguments.output, formatted.as_bytes())
+    } else if formatted == source {
+        Ok(())
+    } else {
+        Err(Failure::Invalid(format!("{}: not formatted\n", file)))
+    }
+}
+
+fn run() -> Result<(), Failure> {
+    let arguments = parse_arguments(std::env::args().skip(1))?;
+    match arguments.command.as_str() {
+        "validate" => validate(&arguments),
+        "render" => render(&arguments),
+        "convert" => convert(&arguments),
+        "stats" => stats(&arguments),
+        "lint" => lint(&arguments),
+        "fmt" => fmt(&arguments),
+        "help" | "--help" | "-h" => write_output(&None, USAGE.as_bytes()),
+        // The command is checked when the arguments are read
+        _ => unreachable!(),
+    }
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(failure) => {
+            if let Failure::Usage(message) = &failure {
+                eprintln!("error: {}\n\n{}", message, USAGE);
+            } else {
+                eprint!("{}", failure.message());
+            }
+            ExitCode::from(failure.exit_code())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn arguments(line: &str) -> Result<Arguments, Failure> {
+        parse_arguments(line.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn command_line() {
+        let parsed = arguments("convert chart.csv --to json --size M -o out.json").ok().unwrap();
+        assert_eq!(parsed.command, "convert");
+        assert_eq!(parsed.file, "chart.csv");
+        assert_eq!(parsed.to, Some(Format::Json));
+        assert_eq!(parsed.size.as_deref(), Some("M"));
+        assert_eq!(parsed.output.as_deref(), Some("out.json"));
+        assert_eq!(Format::from_path(&parsed.file), Format::Csv);
+        assert_eq!(Format::from_path("scarf.knit"), Format::Text);
+        assert!(arguments("fmt scarf.knit --check").ok().unwrap().check);
+
+        for line in ["validate", "frob chart.txt", "render a b", "render a --to gif", "stats a --size"] {
+            assert_eq!(arguments(line).err().map(|failure| failure.exit_code()), Some(2), "{}", line);
+        }
+    }
+
+    #[test]
+    fn diagnostics() {
+        let source = "k x4\nk, pp";
+        let error = Pattern::new(source.as_bytes()).err().unwrap();
+        let message = diagnostic("scarf.txt", &error, Location::Line(error.line_number(), source));
+        assert_eq!(message.message(), "error: invalid syntax\n --> scarf.txt:2:5\n  |\n2 | k, pp\n  |     ^\n");
+    }
+}
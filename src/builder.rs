@@ -0,0 +1,154 @@
+//! Builds a pattern from stitches instead of text.
+
+use std::collections::VecDeque;
+
+use crate::Options;
+use crate::ParseError;
+use crate::ParseErrorType;
+use crate::Pattern;
+use crate::Stitch;
+
+/// A part of a row given to the `PatternBuilder`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Element {
+    /// A single stitch and its color, `None` for the main color.
+    Stitch(Stitch, Option<char>),
+
+    /// A group of elements worked the given number of times, like `(k, p) x2`.
+    Group(Vec<Element>, usize),
+}
+
+impl From<Stitch> for Element {
+    fn from(stitch: Stitch) -> Self {
+        Element::Stitch(stitch, None)
+    }
+}
+
+impl Element {
+    /// Adds the stitches and colors this element works onto the end of the row.
+    fn expand_into(&self, stitches: &mut VecDeque<Stitch>, colors: &mut VecDeque<Option<char>>) {
+        match self {
+            Element::Stitch(stitch, color) => {
+                stitches.push_back(*stitch);
+                colors.push_back(*color);
+            }
+            Element::Group(elements, count) => {
+                for _ in 0..*count {
+                    for element in elements {
+                        element.expand_into(stitches, colors);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Builds a `Pattern` one row at a time.
+///
+/// The rows are checked and padded the same way as `Pattern::new`.
+#[derive(Debug, Default)]
+pub struct PatternBuilder {
+    options: Options,
+    rows: Vec<Vec<Element>>,
+}
+
+impl PatternBuilder {
+    /// Creates a new builder with the default options and no rows.
+    pub fn new() -> PatternBuilder {
+        PatternBuilder::default()
+    }
+
+    /// Sets the options for the pattern.
+    pub fn options(mut self, options: Options) -> PatternBuilder {
+        self.options = options;
+        self
+    }
+
+    /// Adds a row of stitches worked in the main color.
+    pub fn row(mut self, stitches: &[Stitch]) -> PatternBuilder {
+        self.rows.push(stitches.iter().copied().map(Element::from).collect());
+        self
+    }
+
+    /// Adds a row made of stitches and groups.
+    pub fn row_elements(mut self, elements: Vec<Element>) -> PatternBuilder {
+        self.rows.push(elements);
+        self
+    }
+
+    /// Builds the pattern.
+    ///
+    /// A row without any stitches, or one that can't be padded to the width of the others, gives an `InvalidStitchCount` error.
+    pub fn build(self) -> Result<Pattern, ParseError> {
+        let mut lines = Vec::with_capacity(self.rows.len());
+        let mut colors = Vec::with_capacity(self.rows.len());
+
+        for (line_number, row) in self.rows.iter().enumerate() {
+            let mut line = VecDeque::new();
+            let mut line_colors = VecDeque::new();
+            for element in row {
+                element.expand_into(&mut line, &mut line_colors);
+            }
+
+            if line.is_empty() {
+                return Err(ParseError::new(ParseErrorType::InvalidStitchCount(0), line_number));
+            }
+
+            lines.push(line);
+            colors.push(line_colors);
+        }
+
+        Pattern::from_parts(lines, colors, self.options)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Stitch::*;
+
+    #[test]
+    fn matches_text() {
+        let built = PatternBuilder::new()
+            .row(&[K; 5])
+            .row_elements(vec![
+                Element::Group(vec![K.into(), Element::Group(vec![Element::Stitch(P, Some('B')), Ml.into()], 2)], 1),
+                K.into(),
+                K.into(),
+            ])
+            .build()
+            .unwrap();
+        let parsed = Pattern::new(&b"k x5\n(k, (p:B, ml) x2), k, k"[..]).unwrap();
+
+        assert_eq!(built.lines(), parsed.lines());
+        assert_eq!(built.colors(), parsed.colors());
+        assert_eq!(built.pattern_width(), 7);
+    }
+
+    #[test]
+    fn options() {
+        let options = Options {
+            in_round: true,
+            ..Options::default()
+        };
+        let built = PatternBuilder::new().options(options).row(&[K]).build().unwrap();
+
+        assert!(built.in_round());
+    }
+
+    #[test]
+    fn inconsistent_rows() {
+        if let Err(parse_error) = PatternBuilder::new().row(&[K; 4]).row(&[K; 3]).build() {
+            assert_eq!(parse_error.line_number(), 1);
+            if let ParseErrorType::InvalidStitchCount(count) = *parse_error.error_type() {
+                assert_eq!(count, 3);
+            } else {
+                panic!("Wrong error type returned");
+            }
+        } else {
+            panic!("Should not have been valid");
+        }
+
+        assert!(PatternBuilder::new().row(&[]).build().is_err());
+    }
+}
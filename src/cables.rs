@@ -0,0 +1,155 @@
+//! Checks that the cables in a pattern can be worked.
+//!
+//! A cable crosses the stitches that are on the needle, so the columns it covers should have been knit in the row
+//! below, not purled or left empty, and shouldn't include the edge stitch of the row.  Two cables crossing the
+//! same stitches on rows right after each other is also flagged, it's almost always a chart shifted by a column.
+
+use std::fmt;
+
+use crate::Pattern;
+use crate::Stitch;
+
+/// What is wrong with a cable.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum CableIssue {
+    /// The row below has no stitch in the given column.
+    CrossesPadding(usize),
+
+    /// The cable covers the first or last stitch of the row, in the given column.
+    CrossesEdge(usize),
+
+    /// The stitch below the given column was purled.
+    PurledBelow(usize),
+
+    /// The cable crosses stitches that the cable starting at the given column of the row below crossed.
+    Overlaps(usize),
+}
+
+/// A problem with one cable.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct CableProblem {
+    /// The row (index into `Pattern::lines`) the cable is on.
+    pub row: usize,
+
+    /// The first column the cable covers.
+    pub column: usize,
+
+    /// The cable.
+    pub cable: Stitch,
+
+    /// What is wrong with it.
+    pub issue: CableIssue,
+}
+
+impl fmt::Display for CableProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "row {}, column {}: `{}` ", self.row + 1, self.column + 1, self.cable)?;
+        match self.issue {
+            CableIssue::CrossesPadding(column) => write!(f, "crosses column {}, which has no stitch in the row below", column + 1),
+            CableIssue::CrossesEdge(column) => write!(f, "crosses the edge stitch in column {}", column + 1),
+            CableIssue::PurledBelow(column) => write!(f, "crosses column {}, which was purled in the row below", column + 1),
+            CableIssue::Overlaps(column) => write!(f, "crosses the same stitches as the cable at column {} in the row below", column + 1),
+        }
+    }
+}
+
+/// Returns the stitch in each column of the row, with the first column of the stitch it's part of.
+fn columns(line: &[Stitch]) -> Vec<(Stitch, usize)> {
+    let mut columns = Vec::new();
+    for stitch in line {
+        let start = columns.len();
+        columns.extend(std::iter::repeat_n((*stitch, start), stitch.width()));
+    }
+    columns
+}
+
+impl Pattern {
+    /// Checks every cable against the row below it and the edges of its row.
+    ///
+    /// Each problem points at the cable, the column that caused it is given with the issue.
+    pub fn check_cables(&self) -> Vec<CableProblem> {
+        let rows: Vec<Vec<(Stitch, usize)>> = self.lines().iter().map(|line| columns(&line.iter().copied().collect::<Vec<_>>())).collect();
+
+        let mut problems = Vec::new();
+        for (row, line) in rows.iter().enumerate() {
+            let below = row.checked_sub(1).map(|below| &rows[below]);
+            let first = line.iter().position(|(stitch, _)| *stitch != Stitch::NoStitch);
+            let last = line.iter().rposition(|(stitch, _)| *stitch != Stitch::NoStitch);
+
+            for (column, (cable, start)) in line.iter().enumerate() {
+                if cable.width() < 2 || *start != column {
+                    continue;
+                }
+                let mut problem = |issue: CableIssue| {
+                    problems.push(CableProblem {
+                        row,
+                        column,
+                        cable: *cable,
+                        issue,
+                    })
+                };
+                let covered = column..column + cable.width();
+
+                if let Some(edge) = [first, last].into_iter().flatten().find(|edge| covered.contains(edge)) {
+                    problem(CableIssue::CrossesEdge(edge));
+                }
+
+                let Some(below) = below else {
+                    continue;
+                };
+                if let Some(padding) = covered.clone().find(|column| below[*column].0 == Stitch::NoStitch) {
+                    problem(CableIssue::CrossesPadding(padding));
+                }
+                if let Some(purled) = covered.clone().find(|column| below[*column].0.is_purl()) {
+                    problem(CableIssue::PurledBelow(purled));
+                }
+                if let Some(crossed) = covered.clone().find(|column| below[*column].0.width() > 1) {
+                    problem(CableIssue::Overlaps(below[crossed].1));
+                }
+            }
+        }
+
+        problems
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn problems(input: &str) -> Vec<(usize, usize, CableIssue)> {
+        let pattern = Pattern::new(input.as_bytes()).unwrap();
+        pattern
+            .check_cables()
+            .into_iter()
+            .map(|problem| (problem.row, problem.column, problem.issue))
+            .collect()
+    }
+
+    #[test]
+    fn valid() {
+        assert!(problems("p, k x4, p\np, 2rcb, p\np, k x4, p\np, k x4, p").is_empty());
+    }
+
+    #[test]
+    fn problems_found() {
+        assert_eq!(problems("p, k, p, k, p\np, 1lcf, k, p"), vec![(1, 1, CableIssue::PurledBelow(2))]);
+        assert_eq!(
+            problems("k x2\n1rcb, k x2"),
+            vec![(1, 0, CableIssue::CrossesEdge(0)), (1, 0, CableIssue::CrossesPadding(0))]
+        );
+        assert_eq!(
+            problems("p, k x4, p\np, 1rcb, 1rcb, p\np, k, 1lcf, k, p"),
+            vec![(2, 2, CableIssue::Overlaps(1))]
+        );
+    }
+
+    #[test]
+    fn display() {
+        let pattern = Pattern::new("p, k, p, k, p\np, 1lcf, k, p".as_bytes()).unwrap();
+        assert_eq!(
+            pattern.check_cables()[0].to_string(),
+            "row 2, column 2: `1lcf` crosses column 3, which was purled in the row below"
+        );
+    }
+}
@@ -0,0 +1,368 @@
+//! Compares two versions of a pattern.
+
+use std::collections::VecDeque;
+
+use crate::grid::{to_cells, Cell};
+use crate::Lint;
+use crate::Options;
+use crate::Pattern;
+use crate::Side;
+use crate::Stitch;
+
+/// A single column that changed on a row.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct StitchChange {
+    /// The column that changed, counted from the left of the wider of the two rows without their padding.
+    pub column: usize,
+
+    /// The stitch that started at the column before, `None` if the column was covered by a wider stitch or past the end.
+    pub old: Option<Stitch>,
+
+    /// The stitch that starts at the column now, `None` if the column is covered by a wider stitch or past the end.
+    pub new: Option<Stitch>,
+
+    /// The color of the old stitch.
+    pub old_color: Option<char>,
+
+    /// The color of the new stitch.
+    pub new_color: Option<char>,
+}
+
+/// A row that changed between the two patterns.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum RowChange {
+    /// A row only in the new pattern, giving its index there and its stitches and colors without padding.
+    Added(usize, VecDeque<Stitch>, VecDeque<Option<char>>),
+
+    /// A row only in the old pattern, giving its index there and its stitches and colors without padding.
+    Removed(usize, VecDeque<Stitch>, VecDeque<Option<char>>),
+
+    /// A row in both patterns with different stitches, giving the old and new index.
+    Changed(usize, usize, Vec<StitchChange>),
+}
+
+/// An option that changed between the two patterns.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct OptionChange {
+    /// The name of the option, as written after `##`.
+    pub name: &'static str,
+
+    /// The old value.
+    pub old: String,
+
+    /// The new value.
+    pub new: String,
+}
+
+/// The differences between two patterns.
+#[derive(Debug)]
+pub struct PatternDiff {
+    rows: Vec<RowChange>,
+    options: Vec<OptionChange>,
+    old_first_line: usize,
+    new_first_line: usize,
+}
+
+impl PatternDiff {
+    /// Returns the rows that changed, in pattern order.
+    pub fn rows(&self) -> &Vec<RowChange> {
+        &self.rows
+    }
+
+    /// Returns the options that changed.
+    pub fn options(&self) -> &Vec<OptionChange> {
+        &self.options
+    }
+
+    /// Returns if the patterns are the same.
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty() && self.options.is_empty()
+    }
+}
+
+fn cell_text(stitch: Option<Stitch>, color: Option<char>) -> String {
+    match (stitch, color) {
+        (Some(stitch), Some(color)) => format!("{}:{}", stitch, color),
+        (Some(stitch), None) => stitch.to_string(),
+        (None, _) => "-".to_string(),
+    }
+}
+
+/// Writes a row one stitch at a time the same way as the columns of a changed row.
+fn row_text(stitches: &VecDeque<Stitch>, colors: &VecDeque<Option<char>>) -> String {
+    let stitches: Vec<String> = stitches.iter().zip(colors).map(|(stitch, color)| cell_text(Some(*stitch), *color)).collect();
+    stitches.join(", ")
+}
+
+impl std::fmt::Display for PatternDiff {
+    /// Writes the differences a line at a time, similar to a unified diff.
+    ///
+    /// Rows are numbered from each pattern's first line number.
+    fn fmt(&self, out: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for option in &self.options {
+            writeln!(out, "* {}: {} -> {}", option.name, option.old, option.new)?;
+        }
+
+        for row in &self.rows {
+            match row {
+                RowChange::Removed(row, stitches, colors) => writeln!(out, "- row {}: {}", row + self.old_first_line, row_text(stitches, colors))?,
+                RowChange::Added(row, stitches, colors) => writeln!(out, "+ row {}: {}", row + self.new_first_line, row_text(stitches, colors))?,
+                RowChange::Changed(old_row, new_row, changes) => {
+                    writeln!(out, "~ row {} -> row {}:", old_row + self.old_first_line, new_row + self.new_first_line)?;
+                    for change in changes {
+                        writeln!(
+                            out,
+                            "    column {}: {} -> {}",
+                            change.column + 1,
+                            cell_text(change.old, change.old_color),
+                            cell_text(change.new, change.new_color)
+                        )?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn option_changes(old: &Options, new: &Options) -> Vec<OptionChange> {
+    let mut changes = Vec::new();
+    let mut compare = |name: &'static str, old: String, new: String| {
+        if old != new {
+            changes.push(OptionChange { name, old, new });
+        }
+    };
+    let text = |value: &Option<String>| value.clone().unwrap_or_default();
+
+    compare("first_line", old.first_line_number.to_string(), new.first_line_number.to_string());
+    compare(
+        "start_wrong_side",
+        (old.starting_side == Side::WS).to_string(),
+        (new.starting_side == Side::WS).to_string(),
+    );
+    compare("in_round", old.in_round.to_string(), new.in_round.to_string());
+    compare(
+        "gauge",
+        old.gauge.map(|gauge| gauge.to_string()).unwrap_or_default(),
+        new.gauge.map(|gauge| gauge.to_string()).unwrap_or_default(),
+    );
+    compare(
+        "needle_size",
+        old.needle_size.map(|size| size.to_string()).unwrap_or_default(),
+        new.needle_size.map(|size| size.to_string()).unwrap_or_default(),
+    );
+    compare("yarn_weight", text(&old.yarn_weight), text(&new.yarn_weight));
+    compare("title", text(&old.title), text(&new.title));
+    compare("author", text(&old.author), text(&new.author));
+    compare("notes", text(&old.notes), text(&new.notes));
+    compare("sizes", old.sizes.join(", "), new.sizes.join(", "));
+    compare(
+        "allow",
+        old.lints.allowed.iter().map(Lint::name).collect::<Vec<_>>().join(", "),
+        new.lints.allowed.iter().map(Lint::name).collect::<Vec<_>>().join(", "),
+    );
+    compare("max_run", old.lints.max_run.to_string(), new.lints.max_run.to_string());
+
+    changes
+}
+
+/// Compares two rows column by column, centering the narrower one the way a pattern pads rows.
+fn stitch_changes(old: &[Cell], new: &[Cell]) -> Vec<StitchChange> {
+    let width = old.len().max(new.len());
+    let old_offset = (width - old.len()) / 2;
+    let new_offset = (width - new.len()) / 2;
+    let cell_at = |cells: &[Cell], offset: usize, column: usize| -> Option<Cell> { column.checked_sub(offset).and_then(|column| cells.get(column)).copied() };
+    let split = |cell: Option<Cell>| match cell {
+        Some(Cell::Stitch(stitch, color)) => (Some(stitch), color),
+        _ => (None, None),
+    };
+
+    let mut changes = Vec::new();
+    for column in 0..width {
+        let old_cell = cell_at(old, old_offset, column);
+        let new_cell = cell_at(new, new_offset, column);
+        if old_cell != new_cell {
+            let (old, old_color) = split(old_cell);
+            let (new, new_color) = split(new_cell);
+            changes.push(StitchChange {
+                column,
+                old,
+                new,
+                old_color,
+                new_color,
+            });
+        }
+    }
+    changes
+}
+
+impl Pattern {
+    /// Compares this pattern (the old version) with another (the new version).
+    ///
+    /// Rows are lined up by their expanded stitches, so rewriting a row's repeats in a different way doesn't show as a change.
+    /// A run of removed rows followed by added rows is shown as changed rows.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The new version of the pattern
+    ///
+    pub fn diff(&self, other: &Pattern) -> PatternDiff {
+        // Rows are compared without their padding, so a row changing the pattern's width doesn't change every other row.
+        let old_rows: Vec<_> = (0..self.lines().len()).map(|row| self.unpadded_row(row)).collect();
+        let new_rows: Vec<_> = (0..other.lines().len()).map(|row| other.unpadded_row(row)).collect();
+
+        // Longest common subsequence of rows, lengths[i][j] is for old_rows[i..] and new_rows[j..].
+        let mut lengths = vec![vec![0usize; new_rows.len() + 1]; old_rows.len() + 1];
+        for i in (0..old_rows.len()).rev() {
+            for j in (0..new_rows.len()).rev() {
+                lengths[i][j] = if old_rows[i] == new_rows[j] {
+                    lengths[i + 1][j + 1] + 1
+                } else {
+                    lengths[i + 1][j].max(lengths[i][j + 1])
+                };
+            }
+        }
+
+        let mut rows = Vec::new();
+        let mut removed: Vec<usize> = Vec::new();
+        let mut added: Vec<usize> = Vec::new();
+        let flush = |removed: &mut Vec<usize>, added: &mut Vec<usize>, rows: &mut Vec<RowChange>| {
+            let paired = removed.len().min(added.len());
+            for (old_row, new_row) in removed.iter().zip(added.iter()) {
+                let old_cells = to_cells(&old_rows[*old_row].0, &old_rows[*old_row].1);
+                let new_cells = to_cells(&new_rows[*new_row].0, &new_rows[*new_row].1);
+                rows.push(RowChange::Changed(*old_row, *new_row, stitch_changes(&old_cells, &new_cells)));
+            }
+            for old_row in &removed[paired..] {
+                rows.push(RowChange::Removed(*old_row, old_rows[*old_row].0.clone(), old_rows[*old_row].1.clone()));
+            }
+            for new_row in &added[paired..] {
+                rows.push(RowChange::Added(*new_row, new_rows[*new_row].0.clone(), new_rows[*new_row].1.clone()));
+            }
+            removed.clear();
+            added.clear();
+        };
+
+        let (mut i, mut j) = (0, 0);
+        while i < old_rows.len() || j < new_rows.len() {
+            if i < old_rows.len() && j < new_rows.len() && old_rows[i] == new_rows[j] {
+                flush(&mut removed, &mut added, &mut rows);
+                i += 1;
+                j += 1;
+            } else if j == new_rows.len() || (i < old_rows.len() && lengths[i + 1][j] >= lengths[i][j + 1]) {
+                removed.push(i);
+                i += 1;
+            } else {
+                added.push(j);
+                j += 1;
+            }
+        }
+        flush(&mut removed, &mut added, &mut rows);
+
+        PatternDiff {
+            rows,
+            options: option_changes(self.options(), other.options()),
+            old_first_line: self.first_line_number(),
+            new_first_line: other.first_line_number(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Stitch::*;
+
+    fn pattern(input: &str) -> Pattern {
+        Pattern::new(input.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn same_stitches_written_differently() {
+        let diff = pattern("(k, p) x2\nk x4").diff(&pattern("k, p, k, p\n(k x2) x2"));
+        assert!(diff.is_empty());
+    }
+
+    #[test]
+    fn rows() {
+        let diff = pattern("k x4\np x4\nk x4").diff(&pattern("k x4\nk, k2tog, p, k\nk x4\np x4"));
+
+        assert_eq!(
+            diff.rows(),
+            &vec![
+                RowChange::Changed(
+                    1,
+                    1,
+                    vec![
+                        StitchChange {
+                            column: 0,
+                            old: Some(P),
+                            new: Some(K),
+                            old_color: None,
+                            new_color: None,
+                        },
+                        StitchChange {
+                            column: 1,
+                            old: Some(P),
+                            new: Some(K2Tog),
+                            old_color: None,
+                            new_color: None,
+                        },
+                        StitchChange {
+                            column: 3,
+                            old: Some(P),
+                            new: Some(K),
+                            old_color: None,
+                            new_color: None,
+                        },
+                    ]
+                ),
+                RowChange::Added(3, VecDeque::from(vec![P; 4]), VecDeque::from(vec![None; 4])),
+            ]
+        );
+    }
+
+    #[test]
+    fn row_gets_wider() {
+        let diff = pattern("k x4\np x4\nk x4").diff(&pattern("k x4\np x6\nk x4"));
+
+        assert_eq!(
+            diff.rows(),
+            &vec![RowChange::Changed(
+                1,
+                1,
+                vec![
+                    StitchChange {
+                        column: 0,
+                        old: None,
+                        new: Some(P),
+                        old_color: None,
+                        new_color: None,
+                    },
+                    StitchChange {
+                        column: 5,
+                        old: None,
+                        new: Some(P),
+                        old_color: None,
+                        new_color: None,
+                    },
+                ]
+            )]
+        );
+    }
+
+    #[test]
+    fn text() {
+        let diff = pattern("k x2\np x2").diff(&pattern("## in_round\n## first_line=3\nk x2\np:A, p"));
+
+        assert_eq!(diff.options().len(), 2);
+        assert_eq!(
+            diff.to_string(),
+            "* first_line: 1 -> 3\n* in_round: false -> true\n~ row 2 -> row 4:\n    column 1: p -> p:A\n"
+        );
+
+        let diff = pattern("k x4").diff(&pattern("k x4\nnostitch, p:A x2, nostitch"));
+        assert_eq!(diff.to_string(), "+ row 2: nostitch, p:A, p:A, nostitch\n");
+    }
+}
@@ -0,0 +1,268 @@
+//! Edits a pattern in place.
+//!
+//! Every edit pads the rows again afterwards, the same way `Pattern::new` does.  If the edit leaves a
+//! row that can't be padded an `InvalidStitchCount` error is returned and the pattern is left unchanged.
+
+use std::collections::VecDeque;
+use std::ops::Range;
+
+use crate::ParseError;
+use crate::ParseErrorType;
+use crate::Pattern;
+use crate::Stitch;
+
+fn row_out_of_range(row: usize) -> ParseError {
+    ParseError::new(ParseErrorType::RowOutOfRange(row), row)
+}
+
+impl Pattern {
+    /// Returns every row without its padding, ready to be edited and passed to `set_rows`.
+    fn unpadded_rows(&self) -> (Vec<VecDeque<Stitch>>, Vec<VecDeque<Option<char>>>) {
+        (0..self.lines().len()).map(|row| self.unpadded_row(row)).unzip()
+    }
+
+    /// Turns an index into `lines()[row]` into one into the row without its padding.
+    ///
+    /// `end` allows the index just past the last stitch, for the end of a range.
+    fn unpadded_index(&self, row: usize, index: usize, end: bool) -> Result<usize, ParseError> {
+        let line = self.lines().get(row).ok_or_else(|| row_out_of_range(row))?;
+        let padding = self.padding(row);
+        let last = line.len() - padding + end as usize;
+        if index < padding || index >= last {
+            return Err(ParseError::new(ParseErrorType::ColumnOutOfRange(index), row));
+        }
+        Ok(index - padding)
+    }
+
+    /// Insert a row before the given row, an index of `lines().len()` adds it at the end.
+    ///
+    /// # Arguments
+    ///
+    /// * `row` - Where to insert the row
+    /// * `stitches` - The stitches for the row, they are worked in the main color
+    ///
+    pub fn insert_row(&mut self, row: usize, stitches: VecDeque<Stitch>) -> Result<(), ParseError> {
+        if row > self.lines().len() {
+            return Err(row_out_of_range(row));
+        }
+
+        let (mut lines, mut colors) = self.unpadded_rows();
+        colors.insert(row, VecDeque::from(vec![None; stitches.len()]));
+        lines.insert(row, stitches);
+
+        self.set_rows(lines, colors)
+    }
+
+    /// Remove the given row, returning its stitches without any padding.
+    ///
+    /// # Arguments
+    ///
+    /// * `row` - Which row to remove
+    ///
+    pub fn delete_row(&mut self, row: usize) -> Result<VecDeque<Stitch>, ParseError> {
+        if row >= self.lines().len() {
+            return Err(row_out_of_range(row));
+        }
+
+        let (mut lines, mut colors) = self.unpadded_rows();
+        let removed = lines.remove(row);
+        colors.remove(row);
+
+        self.set_rows(lines, colors)?;
+        Ok(removed)
+    }
+
+    /// Move a row so it ends up at the given index.
+    ///
+    /// # Arguments
+    ///
+    /// * `from` - Which row to move
+    /// * `to` - Where the row should end up
+    ///
+    pub fn move_row(&mut self, from: usize, to: usize) -> Result<(), ParseError> {
+        let rows = self.lines().len();
+        if from >= rows {
+            return Err(row_out_of_range(from));
+        }
+        if to >= rows {
+            return Err(row_out_of_range(to));
+        }
+
+        let (mut lines, mut colors) = self.unpadded_rows();
+        let line = lines.remove(from);
+        let line_colors = colors.remove(from);
+        lines.insert(to, line);
+        colors.insert(to, line_colors);
+
+        self.set_rows(lines, colors)
+    }
+
+    /// Replace a single stitch, returning the stitch that was there.
+    ///
+    /// The padding added to line up the rows can't be replaced, an index inside it gives a `ColumnOutOfRange` error.
+    ///
+    /// # Arguments
+    ///
+    /// * `row` - Which row the stitch is on
+    /// * `index` - The index of the stitch within `lines()[row]`
+    /// * `stitch` - The new stitch, it keeps the color of the old one
+    ///
+    pub fn replace_stitch(&mut self, row: usize, index: usize, stitch: Stitch) -> Result<Stitch, ParseError> {
+        let index = self.unpadded_index(row, index, false)?;
+
+        let (mut lines, colors) = self.unpadded_rows();
+        let old = std::mem::replace(&mut lines[row][index], stitch);
+        self.set_rows(lines, colors)?;
+        Ok(old)
+    }
+
+    /// Replace a range of stitches on a row with new ones.
+    ///
+    /// The range can't cover the padding added to line up the rows, if it does a `ColumnOutOfRange` error is given.
+    ///
+    /// # Arguments
+    ///
+    /// * `row` - Which row the stitches are on
+    /// * `range` - The indexes of the stitches within `lines()[row]` to replace
+    /// * `stitches` - The new stitches, they are worked in the main color
+    ///
+    pub fn replace_range(&mut self, row: usize, range: Range<usize>, stitches: Vec<Stitch>) -> Result<(), ParseError> {
+        let start = self.unpadded_index(row, range.start, true)?;
+        let end = self.unpadded_index(row, range.end, true)?;
+        if start > end {
+            return Err(ParseError::new(ParseErrorType::ColumnOutOfRange(range.end), row));
+        }
+
+        let (mut lines, mut colors) = self.unpadded_rows();
+        let new_colors = vec![None; stitches.len()];
+        lines[row].splice_range(start..end, stitches);
+        colors[row].splice_range(start..end, new_colors);
+
+        self.set_rows(lines, colors)
+    }
+
+    /// Set the color of a single stitch.
+    ///
+    /// The padding added to line up the rows can't be colored, an index inside it gives a `ColumnOutOfRange` error.
+    ///
+    /// # Arguments
+    ///
+    /// * `row` - Which row the stitch is on
+    /// * `index` - The index of the stitch within `lines()[row]`
+    /// * `color` - The new color, `None` for the main color
+    ///
+    pub fn set_color(&mut self, row: usize, index: usize, color: Option<char>) -> Result<(), ParseError> {
+        let index = self.unpadded_index(row, index, false)?;
+
+        let (lines, mut colors) = self.unpadded_rows();
+        colors[row][index] = color;
+        self.set_rows(lines, colors)
+    }
+}
+
+/// `VecDeque` has no `splice`, this swaps a range for new values.
+trait SpliceRange<T> {
+    fn splice_range(&mut self, range: Range<usize>, values: Vec<T>);
+}
+
+impl<T> SpliceRange<T> for VecDeque<T> {
+    fn splice_range(&mut self, range: Range<usize>, values: Vec<T>) {
+        let tail = self.split_off(range.end);
+        self.truncate(range.start);
+        self.extend(values);
+        self.extend(tail);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Stitch::*;
+
+    fn pattern(input: &str) -> Pattern {
+        Pattern::new(input.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn insert_and_delete() {
+        let mut edited = pattern("k x2\np x2");
+        edited.insert_row(1, VecDeque::from(vec![K; 4])).unwrap();
+        assert_eq!(edited.pattern_width(), 4);
+        assert_eq!(edited.lines()[0], VecDeque::from(vec![NoStitch, K, K, NoStitch]));
+
+        assert_eq!(edited.delete_row(1).unwrap(), VecDeque::from(vec![K; 4]));
+        assert_eq!(edited.pattern_width(), 2);
+        assert_eq!(edited.lines(), &vec![VecDeque::from(vec![K, K]), VecDeque::from(vec![P, P])]);
+    }
+
+    #[test]
+    fn move_row() {
+        let mut edited = pattern("k\np\nk2tog");
+        edited.move_row(0, 2).unwrap();
+        assert_eq!(
+            edited.lines(),
+            &vec![VecDeque::from(vec![P]), VecDeque::from(vec![K2Tog]), VecDeque::from(vec![K])]
+        );
+        assert!(edited.move_row(0, 3).is_err());
+    }
+
+    #[test]
+    fn replace() {
+        let mut edited = pattern("k:A x4\nk x4");
+        assert_eq!(edited.replace_stitch(0, 1, P).unwrap(), K);
+        assert_eq!(edited.lines()[0], VecDeque::from(vec![K, P, K, K]));
+        assert_eq!(edited.colors()[0][1], Some('A'));
+
+        edited.replace_range(1, 1..3, vec![Lcf2]).unwrap();
+        assert_eq!(edited.pattern_width(), 6);
+        assert_eq!(edited.lines()[1], VecDeque::from(vec![K, Lcf2, K]));
+    }
+
+    #[test]
+    fn bad_edit_is_rolled_back() {
+        let mut edited = pattern("k x4\nk x4");
+
+        if let Err(parse_error) = edited.replace_range(0, 0..1, vec![]) {
+            if let ParseErrorType::InvalidStitchCount(count) = *parse_error.error_type() {
+                assert_eq!(count, 3);
+            } else {
+                panic!("Wrong error type returned");
+            }
+        } else {
+            panic!("Should not have been valid");
+        }
+
+        assert_eq!(edited.lines()[0], VecDeque::from(vec![K; 4]));
+        assert!(edited.replace_stitch(2, 0, P).is_err());
+    }
+
+    #[test]
+    fn written_nostitch_kept() {
+        let mut edited = pattern("nostitch, k x2, nostitch\nk x2");
+        edited.set_color(1, 1, Some('A')).unwrap();
+        assert_eq!(edited.pattern_width(), 4);
+        assert_eq!(edited.lines()[0], VecDeque::from(vec![NoStitch, K, K, NoStitch]));
+        assert_eq!(edited.colors()[1], VecDeque::from(vec![None, Some('A'), None, None]));
+
+        edited.replace_stitch(0, 0, P).unwrap();
+        assert_eq!(edited.lines()[0], VecDeque::from(vec![P, K, K, NoStitch]));
+    }
+
+    #[test]
+    fn padding_cant_be_edited() {
+        let mut edited = pattern("k x4\nk x2");
+        for index in [0, 3] {
+            if let Err(parse_error) = edited.replace_stitch(1, index, P) {
+                assert!(matches!(*parse_error.error_type(), ParseErrorType::ColumnOutOfRange(column) if column == index));
+            } else {
+                panic!("Should not have replaced the padding");
+            }
+        }
+        assert!(edited.set_color(1, 0, Some('A')).is_err());
+        assert!(edited.replace_range(1, 0..2, vec![P]).is_err());
+
+        edited.replace_range(1, 1..1, vec![P, P]).unwrap();
+        assert_eq!(edited.lines()[1], VecDeque::from(vec![P, P, K, K]));
+        assert_eq!(edited.lines()[0], VecDeque::from(vec![K; 4]));
+    }
+}
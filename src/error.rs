@@ -13,6 +13,47 @@ pub enum ParseErrorType {
 
     /// The passed in reader has errored out
     UnableToReadFromReader(Box<dyn Error>),
+
+    /// The requested size is not one of the pattern's sizes, giving the requested size
+    UnknownSize(String),
+
+    /// The pattern doesn't have the text it was read from any more, so its other sizes can't be worked out
+    SizesNotKept,
+
+    /// No repeat could be found in the pattern
+    NoRepeatFound,
+
+    /// The row is not in the pattern, giving the row
+    RowOutOfRange(usize),
+
+    /// The column is not in the pattern, giving the column
+    ColumnOutOfRange(usize),
+
+    /// Cutting at the column would split a wide stitch, giving the column
+    SplitsStitch(usize),
+
+    /// The input uses something that can't be read into a pattern, giving what it was
+    Unsupported(String),
+}
+
+impl ParseErrorType {
+    /// Describes the error in words, for showing to the person who wrote the pattern.
+    pub fn describe(&self) -> String {
+        match self {
+            ParseErrorType::InvalidSyntaxRange(_, _) => "invalid syntax".to_string(),
+            ParseErrorType::InvalidStitchCount(count) => {
+                format!("this row is {} stitches wide, rows have to differ from the widest row by an even number", count)
+            }
+            ParseErrorType::UnableToReadFromReader(error) => format!("couldn't read the pattern: {}", error),
+            ParseErrorType::UnknownSize(size) => format!("`{}` is not one of the pattern's sizes", size),
+            ParseErrorType::SizesNotKept => "the other sizes can't be worked out, the pattern was changed or wasn't read from pattern text".to_string(),
+            ParseErrorType::NoRepeatFound => "no repeat could be found".to_string(),
+            ParseErrorType::RowOutOfRange(row) => format!("row {} is not in the pattern", row),
+            ParseErrorType::ColumnOutOfRange(column) => format!("column {} is not in the pattern", column),
+            ParseErrorType::SplitsStitch(column) => format!("a wide stitch doesn't fit, it's cut at column {}", column),
+            ParseErrorType::Unsupported(what) => format!("`{}` is not supported", what),
+        }
+    }
 }
 
 impl std::fmt::Display for ParseErrorType {
@@ -25,6 +66,13 @@ impl std::fmt::Display for ParseErrorType {
                 range_start, range_end
             ),
             ParseErrorType::UnableToReadFromReader(error) => write!(out, "{{ \"type\" : \"Error reading from stream\", \"underlying\" : \"{}\" }}", error),
+            ParseErrorType::RowOutOfRange(row) => write!(out, "{{ \"type\" : \"Row out of range\", \"row\" : {} }}", row),
+            ParseErrorType::ColumnOutOfRange(column) => write!(out, "{{ \"type\" : \"Column out of range\", \"column\" : {} }}", column),
+            ParseErrorType::SplitsStitch(column) => write!(out, "{{ \"type\" : \"Splits stitch\", \"column\" : {} }}", column),
+            ParseErrorType::NoRepeatFound => write!(out, "{{ \"type\" : \"No repeat found\" }}"),
+            ParseErrorType::Unsupported(what) => write!(out, "{{ \"type\" : \"Unsupported\", \"what\" : \"{}\" }}", what),
+            ParseErrorType::UnknownSize(size) => write!(out, "{{ \"type\" : \"Unknown size\", \"size\" : \"{}\" }}", size),
+            ParseErrorType::SizesNotKept => write!(out, "{{ \"type\" : \"Sizes not kept\" }}"),
         }
     }
 }
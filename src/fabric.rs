@@ -0,0 +1,288 @@
+//! Draws an approximate picture of the knitted fabric as SVG.
+//!
+//! Where a chart gives every stitch a square, the fabric follows `Pattern::lineage`: each stitch is drawn from
+//! the stitches it was worked into up to where it sits on the needle.  Rows that decrease pull in, cables lean
+//! across the stitches they cross and decreases lean the way they are worked.  Knit stitches are drawn as a V, purls
+//! as a bump, yarn overs as a hole.  The gauge, if there is one, sets how tall a row is next to how wide a stitch is.
+
+use crate::render::YARN_COLORS;
+use crate::Pattern;
+use crate::Stitch;
+
+/// How many pixels wide one stitch is.
+const STITCH_WIDTH: f64 = 16.0;
+
+/// How tall a row is next to how wide a stitch is, when the pattern has no gauge.
+const DEFAULT_RATIO: f64 = 0.75;
+
+/// The color of the fabric worked without a color.
+const MAIN_YARN: &str = "#e8dcc0";
+
+/// The color behind the fabric, which shows through holes.
+const BACKGROUND: &str = "#4a4a4a";
+
+fn yarn(color: Option<char>) -> &'static str {
+    match color {
+        None => MAIN_YARN,
+        Some(color) => YARN_COLORS[(color as usize).saturating_sub('A' as usize) % YARN_COLORS.len()],
+    }
+}
+
+fn mean(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        None
+    } else {
+        Some(values.iter().sum::<f64>() / values.len() as f64)
+    }
+}
+
+/// Works out where every stitch left on the needle sits, in stitch widths from the left of the chart.
+///
+/// A stitch sits where the stitches it was worked into sat, spread out for increases and merged for decreases.
+/// New stitches, like yarn overs, sit between their neighbors.  The result is then pulled halfway towards evenly
+/// spaced stitches, the way the fabric relaxes.
+fn positions(pattern: &Pattern) -> Vec<Vec<f64>> {
+    let lineage = pattern.lineage();
+    let center = pattern.pattern_width() as f64 / 2.0;
+
+    let mut rows: Vec<Vec<f64>> = Vec::with_capacity(pattern.lines().len());
+    for row in 0..pattern.lines().len() {
+        let live = lineage.row(row);
+        let below = row.checked_sub(1).map_or(&[][..], |below| rows[below].as_slice());
+
+        let mut raw: Vec<Option<f64>> = Vec::with_capacity(live.len());
+        let mut start = 0;
+        while start < live.len() {
+            let end = start + live[start..].iter().take_while(|other| other.stitch == live[start].stitch).count();
+            let outputs = &live[start..end];
+            let mut taken: Vec<usize> = outputs.iter().flat_map(|output| output.parents.iter().copied()).collect();
+            taken.sort_unstable();
+            taken.dedup();
+            let taken: Vec<f64> = taken.iter().map(|parent| below[*parent]).collect();
+
+            for (offset, output) in outputs.iter().enumerate() {
+                raw.push(if row == 0 {
+                    Some(output.column as f64 + 0.5)
+                } else if taken.len() == outputs.len() {
+                    Some(taken[offset])
+                } else {
+                    mean(&taken).map(|base| base + (offset as f64 - (outputs.len() - 1) as f64 / 2.0) * 0.5)
+                });
+            }
+            start = end;
+        }
+
+        // Fill in the new stitches from their neighbors.
+        let filled: Vec<f64> = (0..raw.len())
+            .map(|index| {
+                raw[index].unwrap_or_else(|| {
+                    let left = raw[..index].iter().rev().flatten().next();
+                    let right = raw[index + 1..].iter().flatten().next();
+                    match (left, right) {
+                        (Some(left), Some(right)) => (left + right) / 2.0,
+                        (Some(left), None) => left + 0.5,
+                        (None, Some(right)) => right - 0.5,
+                        (None, None) => live[index].column as f64 + 0.5,
+                    }
+                })
+            })
+            .collect();
+
+        let count = filled.len() as f64;
+        rows.push(
+            filled
+                .iter()
+                .enumerate()
+                .map(|(index, x)| (x + center + index as f64 - (count - 1.0) / 2.0) / 2.0)
+                .collect(),
+        );
+    }
+    rows
+}
+
+/// Draws one leg of a knit stitch.
+fn leg(out: &mut String, from: (f64, f64), to: (f64, f64), color: &str) {
+    out.push_str(&format!(
+        "  <line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"{}\" stroke-width=\"{:.1}\" stroke-linecap=\"round\"/>\n",
+        from.0,
+        from.1,
+        to.0,
+        to.1,
+        color,
+        STITCH_WIDTH * 0.3
+    ));
+}
+
+/// Draws a knit stitch as a V from the bottom point up to the top of the row.
+fn knit(out: &mut String, top_x: f64, bottom_x: f64, top: f64, bottom: f64, color: &str) {
+    leg(out, (top_x - STITCH_WIDTH * 0.3, top), (bottom_x, bottom), color);
+    leg(out, (top_x + STITCH_WIDTH * 0.3, top), (bottom_x, bottom), color);
+}
+
+/// Draws a purl bump in the middle of the row.
+fn purl(out: &mut String, x: f64, top: f64, bottom: f64, color: &str) {
+    out.push_str(&format!(
+        "  <ellipse cx=\"{:.1}\" cy=\"{:.1}\" rx=\"{:.1}\" ry=\"{:.1}\" fill=\"{}\"/>\n",
+        x,
+        (top + bottom) / 2.0,
+        STITCH_WIDTH * 0.5,
+        (bottom - top) * 0.3,
+        color
+    ));
+}
+
+impl Pattern {
+    /// Draws an approximate picture of the knitted fabric as an SVG image.
+    pub fn render_fabric(&self) -> String {
+        let ratio = self.options().gauge.map_or(DEFAULT_RATIO, |gauge| gauge.stitches / gauge.rows);
+        let row_height = STITCH_WIDTH * ratio;
+        let width = (self.pattern_width() as f64 + 2.0) * STITCH_WIDTH;
+        let height = (self.lines().len() as f64 + 1.0) * row_height;
+        let x = |position: f64| (position + 1.0) * STITCH_WIDTH;
+
+        let mut out = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\" viewBox=\"0 0 {:.0} {:.0}\">\n",
+            width, height, width, height
+        );
+        out.push_str(&format!("  <rect width=\"100%\" height=\"100%\" fill=\"{}\"/>\n", BACKGROUND));
+
+        let lineage = self.lineage();
+        let positions = positions(self);
+        for (row, (line, colors)) in self.lines().iter().zip(self.colors()).enumerate() {
+            let bottom = height - row_height * (row as f64 + 0.5);
+            let top = bottom - row_height;
+            let below = row.checked_sub(1).map_or(&[][..], |below| positions[below].as_slice());
+            let live = lineage.row(row);
+
+            let mut next_live = 0;
+            let mut next_taken = 0;
+            for (index, (stitch, color)) in line.iter().zip(colors).enumerate() {
+                let color = yarn(*color);
+                let taken: Vec<usize> = (next_taken..next_taken + stitch.consumes()).filter(|taken| *taken < below.len()).collect();
+                next_taken += stitch.consumes();
+
+                let mut outputs: Vec<(f64, f64)> = Vec::new();
+                while next_live < live.len() && live[next_live].stitch == index {
+                    let top_x = x(positions[row][next_live]);
+                    let parents: Vec<f64> = live[next_live].parents.iter().map(|parent| x(below[*parent])).collect();
+                    outputs.push((top_x, mean(&parents).unwrap_or(top_x)));
+                    next_live += 1;
+                }
+
+                match stitch {
+                    Stitch::NoStitch => {}
+                    Stitch::Yo => {
+                        for (top_x, _) in outputs {
+                            out.push_str(&format!(
+                                "  <circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"{:.1}\" fill=\"{}\" stroke=\"{}\" stroke-width=\"{:.1}\"/>\n",
+                                top_x,
+                                (top + bottom) / 2.0,
+                                STITCH_WIDTH * 0.3,
+                                BACKGROUND,
+                                color,
+                                STITCH_WIDTH * 0.12
+                            ));
+                        }
+                    }
+                    Stitch::Bo => {
+                        // A bound off stitch lies across the top of the stitch it was worked into.
+                        if let Some(taken) = taken.first() {
+                            out.push_str(&format!(
+                                "  <ellipse cx=\"{:.1}\" cy=\"{:.1}\" rx=\"{:.1}\" ry=\"{:.1}\" fill=\"{}\"/>\n",
+                                x(below[*taken]),
+                                top + row_height * 0.6,
+                                STITCH_WIDTH * 0.5,
+                                row_height * 0.2,
+                                color
+                            ));
+                        }
+                    }
+                    Stitch::Bobble => {
+                        for (top_x, _) in outputs {
+                            out.push_str(&format!(
+                                "  <circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"{:.1}\" fill=\"{}\" stroke=\"{}\"/>\n",
+                                top_x,
+                                (top + bottom) / 2.0,
+                                STITCH_WIDTH * 0.6,
+                                color,
+                                BACKGROUND
+                            ));
+                        }
+                    }
+                    _ if stitch.width() > 1 => {
+                        // The stitches that end up on the side the cable needle was held in front of are drawn last.
+                        let left_in_front = matches!(stitch, Stitch::Lcf1 | Stitch::Lcf2 | Stitch::Lcf3 | Stitch::Lcf4);
+                        outputs.sort_by_key(|(top_x, bottom_x)| (top_x < bottom_x) == left_in_front);
+                        for (top_x, bottom_x) in outputs {
+                            knit(&mut out, top_x, bottom_x, top, bottom, color);
+                        }
+                    }
+                    _ => {
+                        for (top_x, bottom_x) in outputs {
+                            if stitch.is_purl() {
+                                purl(&mut out, top_x, top, bottom, color);
+                            } else {
+                                knit(&mut out, top_x, bottom_x, top, bottom, color);
+                            }
+
+                            // A decrease leans over the stitch it's worked on top of.
+                            let lean = match stitch {
+                                Stitch::K2Tog | Stitch::P2Tog => taken.first().map(|taken| (x(below[*taken]), top_x + STITCH_WIDTH * 0.3)),
+                                Stitch::Ssk | Stitch::Ssp => taken.last().map(|taken| (x(below[*taken]), top_x - STITCH_WIDTH * 0.3)),
+                                _ => None,
+                            };
+                            if let Some((from_x, to_x)) = lean {
+                                leg(&mut out, (from_x, bottom), (to_x, top), color);
+                            }
+                            if *stitch == Stitch::Bead {
+                                out.push_str(&format!(
+                                    "  <circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"{:.1}\" fill=\"#c0c0c0\"/>\n",
+                                    top_x,
+                                    (top + bottom) / 2.0,
+                                    STITCH_WIDTH * 0.2
+                                ));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        out.push_str("</svg>\n");
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn shapes() {
+        let svg = Pattern::new("## gauge=20x40/10cm\nk, p, yo, k2tog\nk:B x4".as_bytes()).unwrap().render_fabric();
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"96\" height=\"24\""));
+        assert_eq!(svg.matches("<ellipse").count(), 1);
+        assert_eq!(svg.matches("<circle").count(), 1);
+        assert_eq!(svg.matches(YARN_COLORS[1]).count(), 8);
+    }
+
+    #[test]
+    fn decreases_pull_in() {
+        let pattern = Pattern::new("k x6\nk, k2tog, ssk, k".as_bytes()).unwrap();
+        let positions = positions(&pattern);
+        assert_eq!(positions[0], vec![0.5, 1.5, 2.5, 3.5, 4.5, 5.5]);
+        assert_eq!(positions[1].len(), 4);
+        assert!(positions[1][0] > positions[0][0] && positions[1][3] < positions[0][5]);
+    }
+
+    #[test]
+    fn cables_cross() {
+        let pattern = Pattern::new("p, k x2, p\np, 1lcf, p".as_bytes()).unwrap();
+        let svg = pattern.render_fabric();
+        let moving_left = svg.find("x1=\"35.2\" y1=\"6.0\" x2=\"56.0\" y2=\"18.0\"").unwrap();
+        let moving_right = svg.find("x1=\"51.2\" y1=\"6.0\" x2=\"40.0\" y2=\"18.0\"").unwrap();
+
+        // The stitches moving left are held in front for a left cross, so they're drawn on top.
+        assert!(moving_right < moving_left);
+    }
+}
@@ -0,0 +1,171 @@
+//! Rewrites pattern source files in one canonical layout.
+//!
+//! Stitches are separated by `, `, counts are written as `k x3` and `(k, p) x2`, and counts with a number per
+//! size as `k x10 (12, 14)`.  Options lines are written as `## name=value` and sorted by name, taking the places
+//! the options lines had in the file so comments and rows stay where they were.
+
+use nom::{character::complete::char, multi::separated_list1};
+
+use crate::parse_line::{item, Count, Item};
+use crate::util::into_parse_error;
+use crate::ParseError;
+use crate::Pattern;
+
+fn write_count(out: &mut String, count: &Option<Count>) {
+    if let Some(count) = count {
+        out.push_str(&format!(" x{}", count.first));
+        if !count.alternatives.is_empty() {
+            let alternatives: Vec<String> = count.alternatives.iter().map(usize::to_string).collect();
+            out.push_str(&format!(" ({})", alternatives.join(", ")));
+        }
+    }
+}
+
+fn write_items(out: &mut String, items: &[Item]) {
+    for (index, item) in items.iter().enumerate() {
+        if index > 0 {
+            out.push_str(", ");
+        }
+        match item {
+            Item::Stitch(stitch, color, count) => {
+                out.push_str(&stitch.to_string());
+                if let Some(color) = color {
+                    out.push_str(&format!(":{}", color));
+                }
+                write_count(out, count);
+            }
+            Item::Group(items, count) => {
+                out.push('(');
+                write_items(out, items);
+                out.push(')');
+                write_count(out, count);
+            }
+        }
+    }
+}
+
+/// Formats a line of stitches.
+fn format_stitches(line: &str, line_number: usize) -> Result<String, ParseError> {
+    match separated_list1(char(','), item)(line) {
+        Ok(("", items)) => {
+            let mut out = String::new();
+            write_items(&mut out, &items);
+            Ok(out)
+        }
+        Ok((rest, _)) => Err(into_parse_error(line, rest, line_number)),
+        Err(_) => Err(into_parse_error(line, line, line_number)),
+    }
+}
+
+/// Formats an options line, which has already been checked to be valid.
+fn format_option(line: &str) -> (String, String) {
+    let option = line[2..].trim();
+    match option.split_once('=') {
+        Some((name, value)) => {
+            let name = name.trim();
+            let value = if matches!(name, "sizes" | "allow" | "warn") {
+                value.split(',').map(str::trim).collect::<Vec<_>>().join(", ")
+            } else {
+                value.trim().to_string()
+            };
+            // `allow` and `warn` undo each other, so they keep their order between themselves.
+            let key = if name == "warn" { "allow" } else { name };
+            (key.to_string(), format!("## {}={}", name, value))
+        }
+        None => (option.to_string(), format!("## {}", option)),
+    }
+}
+
+/// Rewrites the text of a pattern in the canonical layout.
+///
+/// The pattern has to be valid, the error from reading it is returned otherwise.
+///
+/// # Arguments
+///
+/// * `text` - The pattern source to format
+///
+pub fn format_pattern(text: &str) -> Result<String, ParseError> {
+    Pattern::new(text.as_bytes())?;
+
+    let mut lines: Vec<String> = Vec::new();
+    let mut option_places: Vec<usize> = Vec::new();
+    let mut options: Vec<(String, String)> = Vec::new();
+    for (line_number, line) in (1..).zip(text.lines()) {
+        if line.starts_with("##") {
+            option_places.push(lines.len());
+            options.push(format_option(line));
+            lines.push(String::new());
+        } else if line.starts_with('#') {
+            lines.push(line.trim_end().to_string());
+        } else {
+            lines.push(format_stitches(line, line_number)?);
+        }
+    }
+
+    // The sort is stable so repeated options, like notes, keep their order.
+    options.sort_by(|a, b| a.0.cmp(&b.0));
+    for (place, (_, option)) in option_places.into_iter().zip(options) {
+        lines[place] = option;
+    }
+
+    let mut out = String::new();
+    for line in lines {
+        out.push_str(&line);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Returns if the text of a pattern is already in the canonical layout.
+///
+/// # Arguments
+///
+/// * `text` - The pattern source to check
+///
+pub fn is_formatted(text: &str) -> Result<bool, ParseError> {
+    Ok(format_pattern(text)? == text)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ParseErrorType;
+
+    #[test]
+    fn stitches() {
+        let formatted = format_pattern("k x3,p:A,( k , (p,yo)x2 ) x2\n  ssk,k2tog x1(2,3)\n## sizes = S,M, L\n").unwrap();
+        assert_eq!(formatted, "k x3, p:A, (k, (p, yo) x2) x2\nssk, k2tog x1 (2, 3)\n## sizes=S, M, L\n");
+        assert!(is_formatted(&formatted).unwrap());
+    }
+
+    #[test]
+    fn options_and_comments() {
+        let text = "##  title=Rib \n# cast on 4\n## notes=Second\nk, p\n## author=Me\n##in_round\n## notes=Third\nk, p\n";
+        let formatted = format_pattern(text).unwrap();
+        assert_eq!(
+            formatted,
+            "## author=Me\n# cast on 4\n## in_round\nk, p\n## notes=Second\n## notes=Third\n## title=Rib\nk, p\n"
+        );
+        assert!(!is_formatted(text).unwrap());
+        assert_eq!(format_pattern(&formatted).unwrap(), formatted);
+    }
+
+    #[test]
+    fn allow_and_warn() {
+        let formatted = format_pattern("## warn=long_run\n## title=Rib\n## allow=long_run,L001\nk").unwrap();
+        assert_eq!(formatted, "## warn=long_run\n## allow=long_run, L001\n## title=Rib\nk\n");
+    }
+
+    #[test]
+    fn invalid() {
+        if let Err(parse_error) = format_pattern("k, p\nk, q") {
+            if let ParseErrorType::InvalidSyntaxRange(..) = *parse_error.error_type() {
+                assert_eq!(parse_error.line_number(), 2);
+            } else {
+                panic!("Wrong error type returned");
+            }
+        } else {
+            panic!("Should not have been valid");
+        }
+    }
+}
@@ -0,0 +1,86 @@
+//! Gauge and the finished dimensions worked out from it.
+
+/// How many centimeters are in an inch.
+const CM_PER_INCH: f64 = 2.54;
+
+/// The length a gauge is measured over.
+#[derive(Debug, PartialEq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GaugeLength {
+    /// Measured over 10 cm.
+    #[cfg_attr(feature = "serde", serde(rename = "10cm"))]
+    TenCm,
+
+    /// Measured over 4 inches.
+    #[cfg_attr(feature = "serde", serde(rename = "4in"))]
+    FourInches,
+}
+
+impl GaugeLength {
+    /// Returns the length in centimeters.
+    pub fn cm(&self) -> f64 {
+        match self {
+            GaugeLength::TenCm => 10.0,
+            GaugeLength::FourInches => 4.0 * CM_PER_INCH,
+        }
+    }
+}
+
+/// The gauge of a pattern.
+#[derive(Debug, PartialEq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Gauge {
+    /// Stitches over `length`.
+    pub stitches: f64,
+
+    /// Rows over `length`.
+    pub rows: f64,
+
+    /// What length the stitches and rows were measured over.
+    pub length: GaugeLength,
+}
+
+impl Gauge {
+    /// Returns how wide a single stitch is in centimeters.
+    pub fn stitch_width_cm(&self) -> f64 {
+        self.length.cm() / self.stitches
+    }
+
+    /// Returns how tall a single row is in centimeters.
+    pub fn row_height_cm(&self) -> f64 {
+        self.length.cm() / self.rows
+    }
+}
+
+impl std::fmt::Display for Gauge {
+    /// Writes the gauge the same way as the `gauge` option, such as `22x30/10cm`.
+    fn fmt(&self, out: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let length = match self.length {
+            GaugeLength::TenCm => "10cm",
+            GaugeLength::FourInches => "4in",
+        };
+        write!(out, "{}x{}/{}", self.stitches, self.rows, length)
+    }
+}
+
+/// The finished size of a piece.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Dimensions {
+    /// The width in centimeters.
+    pub width_cm: f64,
+
+    /// The height in centimeters.
+    pub height_cm: f64,
+}
+
+impl Dimensions {
+    /// Returns the width in inches.
+    pub fn width_in(&self) -> f64 {
+        self.width_cm / CM_PER_INCH
+    }
+
+    /// Returns the height in inches.
+    pub fn height_in(&self) -> f64 {
+        self.height_cm / CM_PER_INCH
+    }
+}
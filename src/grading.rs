@@ -0,0 +1,185 @@
+//! Resizes a stitch pattern to a target width by changing how many times its repeat is worked.
+
+use crate::grid::Cell;
+use crate::repeat::find_horizontal_repeat;
+use crate::ParseError;
+use crate::ParseErrorType;
+use crate::Pattern;
+use crate::Stitch;
+
+/// What was changed to grade a pattern.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct GradingReport {
+    /// How many columns one repeat covers.
+    pub repeat_width: usize,
+
+    /// How many times the repeat was worked before grading.
+    pub original_repeats: usize,
+
+    /// How many times the repeat is worked after grading.
+    pub repeats: usize,
+
+    /// How many edge columns were on the left of the repeats before grading.
+    pub left_edge: usize,
+
+    /// How many edge columns were on the right of the repeats before grading.
+    pub right_edge: usize,
+
+    /// How many columns were added to the left edge to reach the target width.
+    pub added_left: usize,
+
+    /// How many columns were added to the right edge to reach the target width.
+    pub added_right: usize,
+}
+
+impl std::fmt::Display for GradingReport {
+    fn fmt(&self, out: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            out,
+            "Repeat of {} stitches worked {} times (was {}). Kept {} edge stitches on the left and {} on the right, added {} on the left and {} on the right.",
+            self.repeat_width, self.repeats, self.original_repeats, self.left_edge, self.right_edge, self.added_left, self.added_right
+        )
+    }
+}
+
+/// Returns the cells continuing the repeat outwards, swapping any cut up wide stitch for knits.
+fn edge_cells(unit: &[Cell], count: usize, from_end: bool) -> Vec<Cell> {
+    let start = if from_end { unit.len() - count } else { 0 };
+    let mut cells: Vec<Cell> = unit[start..start + count].to_vec();
+
+    // A wide stitch cut on its left leaves continued cells at the start.
+    for cell in cells.iter_mut().take_while(|cell| **cell == Cell::Continued) {
+        *cell = Cell::Stitch(Stitch::K, None);
+    }
+
+    // A wide stitch cut on its right is wider than what's left.
+    let mut column = 0;
+    while column < cells.len() {
+        if let Cell::Stitch(stitch, _) = cells[column] {
+            if column + stitch.width() > cells.len() {
+                for cell in &mut cells[column..] {
+                    *cell = Cell::Stitch(Stitch::K, None);
+                }
+                break;
+            }
+        }
+        column += 1;
+    }
+
+    cells
+}
+
+impl Pattern {
+    /// Grade the pattern to the given width by working its repeat more or fewer times.
+    ///
+    /// Edge stitches outside the repeat are kept.  Any columns left over are split between both
+    /// edges, continuing the repeat outwards so the pattern stays balanced.
+    ///
+    /// # Arguments
+    ///
+    /// * `target_width` - How many columns wide the new pattern should be
+    ///
+    pub fn grade(&self, target_width: usize) -> Result<(Pattern, GradingReport), ParseError> {
+        let rows = self.cells();
+        let repeat = match find_horizontal_repeat(&rows) {
+            Some(repeat) => repeat,
+            None => return Err(ParseError::new(ParseErrorType::NoRepeatFound, 0)),
+        };
+
+        let left_edge = repeat.start_column;
+        let right_edge = self.pattern_width() - repeat.end_column();
+        if target_width < left_edge + right_edge + repeat.width {
+            return Err(ParseError::new(ParseErrorType::InvalidStitchCount(target_width), 0));
+        }
+
+        let available = target_width - left_edge - right_edge;
+        let repeats = available / repeat.width;
+        let extra = available % repeat.width;
+        let added_left = extra / 2;
+        let added_right = extra - added_left;
+
+        let mut graded_rows = Vec::with_capacity(rows.len());
+        for (index, row) in rows.iter().enumerate() {
+            let unit = &row[repeat.start_column..repeat.start_column + repeat.width];
+
+            let mut graded = Vec::with_capacity(target_width);
+            graded.extend_from_slice(&row[..left_edge]);
+            graded.extend(edge_cells(unit, added_left, true));
+            for _ in 0..repeats {
+                graded.extend_from_slice(unit);
+            }
+            graded.extend(edge_cells(unit, added_right, false));
+            graded.extend_from_slice(&row[repeat.end_column()..]);
+
+            // The row's padding is in its edges, drop it so the graded row is padded again around its new width.
+            let padding = self.padding(index).min(left_edge).min(right_edge);
+            graded_rows.push(graded[padding..graded.len() - padding].to_vec());
+        }
+
+        let pattern = self.with_cells(&graded_rows)?;
+        let report = GradingReport {
+            repeat_width: repeat.width,
+            original_repeats: repeat.count,
+            repeats,
+            left_edge,
+            right_edge,
+            added_left,
+            added_right,
+        };
+
+        Ok((pattern, report))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::collections::VecDeque;
+
+    #[test]
+    fn more_repeats() {
+        let input = b"k, (p x2, k x2) x3, k\nk, (k x2, p x2) x3, k";
+        let pattern = Pattern::new(&input[..]).unwrap();
+        let (graded, report) = pattern.grade(22).unwrap();
+
+        assert_eq!(graded.pattern_width(), 22);
+        assert_eq!(report.repeats, 5);
+        assert_eq!(report.original_repeats, 3);
+        assert_eq!(report.added_left + report.added_right, 0);
+    }
+
+    #[test]
+    fn balanced_edges() {
+        let input = b"(k, p x3) x2";
+        let pattern = Pattern::new(&input[..]).unwrap();
+        let (graded, report) = pattern.grade(10).unwrap();
+
+        assert_eq!(report.repeats, 2);
+        assert_eq!(report.added_left, 1);
+        assert_eq!(report.added_right, 1);
+
+        use crate::Stitch::*;
+        assert_eq!(graded.lines()[0], VecDeque::from(vec![P, K, P, P, P, K, P, P, P, K]));
+    }
+
+    #[test]
+    fn padded_rows() {
+        let input = b"k x2, (p, k) x3, k x2\nk, (p, k) x3, k";
+        let (graded, _) = Pattern::new(&input[..]).unwrap().grade(12).unwrap();
+
+        assert_eq!(graded.pattern_width(), 12);
+        assert_eq!(graded.padding(1), 1);
+        assert_eq!(graded.unpadded_row(1).0.len(), 10);
+        assert!(!graded.unpadded_row(1).0.contains(&crate::Stitch::NoStitch));
+    }
+
+    #[test]
+    fn too_narrow() {
+        let input = b"k, (p, k) x4, k";
+        let pattern = Pattern::new(&input[..]).unwrap();
+
+        assert!(pattern.grade(3).is_err());
+        assert!(Pattern::new(&b"k, p2tog"[..]).unwrap().grade(10).is_err());
+    }
+}
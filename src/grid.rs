@@ -0,0 +1,69 @@
+//! Lays the stitches of a pattern out by column.
+//!
+//! Lines hold one entry per stitch, but a cable covers more than one column.  The grid has one
+//! cell per column so patterns can be cut up and compared column by column.
+
+use std::collections::VecDeque;
+
+use crate::ParseError;
+use crate::Pattern;
+use crate::Stitch;
+
+/// One column of a row.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum Cell {
+    /// The first column of a stitch and its color.
+    Stitch(Stitch, Option<char>),
+
+    /// A column covered by a wider stitch to the left.
+    Continued,
+}
+
+/// Lays out a row of stitches and colors into cells.
+pub fn to_cells(stitches: &VecDeque<Stitch>, colors: &VecDeque<Option<char>>) -> Vec<Cell> {
+    let mut cells = Vec::with_capacity(stitches.len());
+    for (stitch, color) in stitches.iter().zip(colors) {
+        cells.push(Cell::Stitch(*stitch, *color));
+        for _ in 1..stitch.width() {
+            cells.push(Cell::Continued);
+        }
+    }
+    cells
+}
+
+/// Turns cells back into a row of stitches and colors.
+///
+/// Any `Continued` cell without a stitch before it is dropped, use `splits_stitch` first to avoid this.
+pub fn from_cells(cells: &[Cell]) -> (VecDeque<Stitch>, VecDeque<Option<char>>) {
+    let mut stitches = VecDeque::with_capacity(cells.len());
+    let mut colors = VecDeque::with_capacity(cells.len());
+    for cell in cells {
+        if let Cell::Stitch(stitch, color) = cell {
+            stitches.push_back(*stitch);
+            colors.push_back(*color);
+        }
+    }
+    (stitches, colors)
+}
+
+/// Returns if cutting the row just before `column` would split a wide stitch.
+pub fn splits_stitch(cells: &[Cell], column: usize) -> bool {
+    matches!(cells.get(column), Some(Cell::Continued))
+}
+
+impl Pattern {
+    /// Returns the pattern laid out with one cell per column.
+    pub(crate) fn cells(&self) -> Vec<Vec<Cell>> {
+        self.lines()
+            .iter()
+            .zip(self.colors())
+            .map(|(stitches, colors)| to_cells(stitches, colors))
+            .collect()
+    }
+
+    /// Create a new Pattern from rows of cells worked out from this one, keeping its options and size.
+    pub(crate) fn with_cells(&self, rows: &[Vec<Cell>]) -> Result<Pattern, ParseError> {
+        let (lines, colors) = rows.iter().map(|row| from_cells(row)).unzip();
+        self.with_rows(lines, colors)
+    }
+}
@@ -0,0 +1,236 @@
+//! Finds the color regions of a flat colorwork pattern and works out the bobbins needed for intarsia.
+
+use crate::Pattern;
+use crate::Stitch;
+
+/// A run of stitches in the same color within a single row.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct ColorRun {
+    /// The color of the run, `None` is the main color.
+    pub color: Option<char>,
+
+    /// The first column of the run.
+    pub start_column: usize,
+
+    /// How many columns the run covers.
+    pub width: usize,
+
+    /// Which yarn source (bobbin) works this run.
+    pub source: usize,
+}
+
+impl ColorRun {
+    fn end_column(&self) -> usize {
+        self.start_column + self.width
+    }
+
+    fn overlaps(&self, other: &ColorRun) -> bool {
+        self.start_column < other.end_column() && other.start_column < self.end_column()
+    }
+}
+
+/// A place where a yarn would need to be carried across the back of the work.
+///
+/// This happens when a color shows up in a row away from where its bobbin was left on the row before.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Float {
+    /// The row (index into `Pattern::lines`) the float happens on.
+    pub row: usize,
+
+    /// The color being carried, `None` is the main color.
+    pub color: Option<char>,
+
+    /// The column the yarn was left at.
+    pub from_column: usize,
+
+    /// The column the yarn is needed at.
+    pub to_column: usize,
+}
+
+/// The result of an intarsia analysis.
+#[derive(Debug)]
+pub struct IntarsiaReport {
+    runs: Vec<Vec<ColorRun>>,
+    yarn_sources: usize,
+    floats: Vec<Float>,
+}
+
+impl IntarsiaReport {
+    /// Returns the color runs for each row.
+    pub fn runs(&self) -> &Vec<Vec<ColorRun>> {
+        &self.runs
+    }
+
+    /// Returns how many bobbins are being worked on the given row.
+    pub fn bobbins(&self, row: usize) -> usize {
+        self.runs.get(row).map_or(0, |runs| runs.len())
+    }
+
+    /// Returns how many bobbins are being worked on each row.
+    pub fn bobbins_per_row(&self) -> Vec<usize> {
+        self.runs.iter().map(|runs| runs.len()).collect()
+    }
+
+    /// Returns the total number of yarn sources (bobbins to wind) for the whole pattern.
+    pub fn yarn_sources(&self) -> usize {
+        self.yarn_sources
+    }
+
+    /// Returns the places where a float would be needed.
+    pub fn floats(&self) -> &Vec<Float> {
+        &self.floats
+    }
+
+    /// Returns if the pattern can be worked in intarsia without any floats.
+    pub fn is_workable(&self) -> bool {
+        self.floats.is_empty()
+    }
+}
+
+/// Splits a row into runs of the same color, `NoStitch` columns break runs and are skipped.
+fn row_runs(stitches: impl Iterator<Item = (Stitch, Option<char>)>) -> Vec<ColorRun> {
+    let mut runs: Vec<ColorRun> = Vec::new();
+    let mut column = 0;
+    let mut previous_was_stitch = false;
+
+    for (stitch, color) in stitches {
+        let width = stitch.width();
+
+        if stitch == Stitch::NoStitch {
+            previous_was_stitch = false;
+        } else {
+            match runs.last_mut() {
+                Some(run) if previous_was_stitch && run.color == color => run.width += width,
+                _ => runs.push(ColorRun {
+                    color,
+                    start_column: column,
+                    width,
+                    source: 0,
+                }),
+            }
+            previous_was_stitch = true;
+        }
+
+        column += width;
+    }
+
+    runs
+}
+
+impl Pattern {
+    /// Finds the contiguous color regions and how many bobbins are needed to work them in intarsia.
+    ///
+    /// A run keeps the bobbin of an overlapping run of the same color on the row before.  If the
+    /// color only shows up elsewhere on the row before, that bobbin is reused but a `Float` is reported.
+    /// Otherwise a new yarn source is started.
+    ///
+    /// This is meant for patterns worked flat, in the round colorwork is normally stranded.
+    pub fn intarsia(&self) -> IntarsiaReport {
+        let mut runs: Vec<Vec<ColorRun>> = Vec::with_capacity(self.lines().len());
+        let mut yarn_sources = 0;
+        let mut floats = Vec::new();
+
+        for (row, (stitches, colors)) in self.lines().iter().zip(self.colors()).enumerate() {
+            let mut row_runs = row_runs(stitches.iter().copied().zip(colors.iter().copied()));
+            let previous_runs: &[ColorRun] = runs.last().map_or(&[], |runs| runs.as_slice());
+            let mut claimed = vec![false; previous_runs.len()];
+
+            // First pass keeps bobbins that sit right below.
+            let mut unmatched = Vec::new();
+            for (index, run) in row_runs.iter_mut().enumerate() {
+                let below = previous_runs
+                    .iter()
+                    .enumerate()
+                    .find(|(previous_index, previous)| !claimed[*previous_index] && previous.color == run.color && previous.overlaps(run));
+
+                match below {
+                    Some((previous_index, previous)) => {
+                        claimed[previous_index] = true;
+                        run.source = previous.source;
+                    }
+                    None => unmatched.push(index),
+                }
+            }
+
+            // Second pass either carries a left over bobbin across or starts a new one.
+            for index in unmatched {
+                let run = &mut row_runs[index];
+                let left_over = previous_runs
+                    .iter()
+                    .enumerate()
+                    .filter(|(previous_index, previous)| !claimed[*previous_index] && previous.color == run.color)
+                    .min_by_key(|(_, previous)| previous.start_column.abs_diff(run.start_column));
+
+                match left_over {
+                    Some((previous_index, previous)) => {
+                        claimed[previous_index] = true;
+                        run.source = previous.source;
+
+                        let (from_column, to_column) = if previous.end_column() <= run.start_column {
+                            (previous.end_column() - 1, run.start_column)
+                        } else {
+                            (previous.start_column, run.end_column() - 1)
+                        };
+                        floats.push(Float {
+                            row,
+                            color: run.color,
+                            from_column,
+                            to_column,
+                        });
+                    }
+                    None => {
+                        run.source = yarn_sources;
+                        yarn_sources += 1;
+                    }
+                }
+            }
+
+            runs.push(row_runs);
+        }
+
+        IntarsiaReport { runs, yarn_sources, floats }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn single_block() {
+        let input = b"k x2, k:A x2, k x2\nk x2, k:A x2, k x2";
+        let report = Pattern::new(&input[..]).unwrap().intarsia();
+
+        assert_eq!(report.bobbins_per_row(), vec![3, 3]);
+        assert_eq!(report.yarn_sources(), 3);
+        assert!(report.is_workable());
+    }
+
+    #[test]
+    fn region_splits() {
+        let input = b"k:A x6\nk:A x2, k x2, k:A x2";
+        let report = Pattern::new(&input[..]).unwrap().intarsia();
+
+        assert_eq!(report.bobbins_per_row(), vec![1, 3]);
+        assert_eq!(report.yarn_sources(), 3);
+        assert_eq!(report.runs()[1][2].start_column, 4);
+    }
+
+    #[test]
+    fn needs_float() {
+        let input = b"k:A x2, k x4\nk x4, k:A x2";
+        let report = Pattern::new(&input[..]).unwrap().intarsia();
+
+        assert_eq!(report.yarn_sources(), 2);
+        assert_eq!(
+            report.floats(),
+            &vec![Float {
+                row: 1,
+                color: Some('A'),
+                from_column: 1,
+                to_column: 4,
+            }]
+        );
+        assert!(!report.is_workable());
+    }
+}
@@ -0,0 +1,461 @@
+//! Reads and writes KnitML, turned on with the `knitml` feature.
+//!
+//! Each chart row is written as a KnitML `row` in the order it's worked, so right side rows are read from
+//! the chart right to left and wrong side rows left to right using the wrong side stitches.  The stitches are
+//! written as:
+//!
+//! | Stitch | KnitML |
+//! |--------|--------|
+//! | `k`, `p` | `<knit>n</knit>`, `<purl>n</purl>` |
+//! | `ktbl`, `ptbl` | `<knit loop-to-work="trailing">n</knit>`, `<purl loop-to-work="trailing">n</purl>` |
+//! | `sl kwise`, `sl pwise` | `<slip type="knitwise">n</slip>`, `<slip type="purlwise">n</slip>` |
+//! | `bo` | `<bind-off>n</bind-off>` |
+//! | `k2tog`, `p2tog`, `ssk`, `ssp` | `<decrease type="k2tog"/>` and so on |
+//! | `yo`, `ml`, `mr`, `m kwise`, `m pwise` | `<increase type="yo"/>`, `m1l`, `m1r`, `m1`, `m1p` |
+//! | `kfb`, `kbf`, `pfb`, `pbf` | `<increase type="kfb"/>` and so on |
+//! | `1lcf`, `1rcb` ... `4lcf`, `4rcb` | `<cross-stitches first="1" next="1" type="front"/>`, `type="back"` |
+//!
+//! `bobble` and `bead` have no KnitML version.  Colors are written as a `yarn-ref` on each stitch, and each
+//! color used is declared as a `yarn` with the color as its id in `supplies`.  The
+//! `title`, `author` and `notes` options go in `general-information`, `in_round` is the instruction's `shape`
+//! and the first row's `number` and `side` give `first_line` and `start_wrong_side`.  The other options are
+//! not written.
+
+use std::collections::VecDeque;
+use std::io::Read;
+
+use roxmltree::{Document, Node};
+
+use crate::Options;
+use crate::ParseError;
+use crate::ParseErrorType;
+use crate::Pattern;
+use crate::Side;
+use crate::Stitch;
+
+const NAMESPACE: &str = "http://www.knitml.com/schema/pattern";
+
+/// Returns the KnitML element and attributes a stitch is written as, `None` if KnitML doesn't have it.
+fn directive(stitch: Stitch) -> Option<(&'static str, &'static [(&'static str, &'static str)])> {
+    let directive: (&'static str, &'static [(&'static str, &'static str)]) = match stitch {
+        Stitch::K => ("knit", &[]),
+        Stitch::P => ("purl", &[]),
+        Stitch::Ktbl => ("knit", &[("loop-to-work", "trailing")]),
+        Stitch::Ptbl => ("purl", &[("loop-to-work", "trailing")]),
+        Stitch::SlKwise => ("slip", &[("type", "knitwise")]),
+        Stitch::SlPwise => ("slip", &[("type", "purlwise")]),
+        Stitch::Bo => ("bind-off", &[]),
+
+        Stitch::K2Tog => ("decrease", &[("type", "k2tog")]),
+        Stitch::P2Tog => ("decrease", &[("type", "p2tog")]),
+        Stitch::Ssk => ("decrease", &[("type", "ssk")]),
+        Stitch::Ssp => ("decrease", &[("type", "ssp")]),
+
+        Stitch::Yo => ("increase", &[("type", "yo")]),
+        Stitch::Ml => ("increase", &[("type", "m1l")]),
+        Stitch::Mr => ("increase", &[("type", "m1r")]),
+        Stitch::MKwise => ("increase", &[("type", "m1")]),
+        Stitch::MPwise => ("increase", &[("type", "m1p")]),
+        Stitch::Kfb => ("increase", &[("type", "kfb")]),
+        Stitch::Kbf => ("increase", &[("type", "kbf")]),
+        Stitch::Pfb => ("increase", &[("type", "pfb")]),
+        Stitch::Pbf => ("increase", &[("type", "pbf")]),
+
+        Stitch::Lcf1 => ("cross-stitches", &[("first", "1"), ("next", "1"), ("type", "front")]),
+        Stitch::Rcb1 => ("cross-stitches", &[("first", "1"), ("next", "1"), ("type", "back")]),
+        Stitch::Lcf2 => ("cross-stitches", &[("first", "2"), ("next", "2"), ("type", "front")]),
+        Stitch::Rcb2 => ("cross-stitches", &[("first", "2"), ("next", "2"), ("type", "back")]),
+        Stitch::Lcf3 => ("cross-stitches", &[("first", "3"), ("next", "3"), ("type", "front")]),
+        Stitch::Rcb3 => ("cross-stitches", &[("first", "3"), ("next", "3"), ("type", "back")]),
+        Stitch::Lcf4 => ("cross-stitches", &[("first", "4"), ("next", "4"), ("type", "front")]),
+        Stitch::Rcb4 => ("cross-stitches", &[("first", "4"), ("next", "4"), ("type", "back")]),
+
+        Stitch::NoStitch | Stitch::Bobble | Stitch::Bead => return None,
+    };
+    Some(directive)
+}
+
+/// Returns if the KnitML element holds a count of stitches.
+fn is_counted(name: &str) -> bool {
+    matches!(name, "knit" | "purl" | "slip" | "bind-off")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Returns the line of the XML the node starts on.
+fn node_line(node: Node) -> usize {
+    node.document().text_pos_at(node.range().start).row as usize
+}
+
+fn unsupported(what: String, node: Node) -> ParseError {
+    ParseError::new(ParseErrorType::Unsupported(what), node_line(node))
+}
+
+impl Pattern {
+    /// Writes the pattern as a KnitML document.
+    ///
+    /// A pattern with a `bobble` or `bead` gives an `Unsupported` error, as KnitML has no way to write them.
+    pub fn to_knitml(&self) -> Result<String, ParseError> {
+        let mut out = String::new();
+        out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        out.push_str(&format!("<pattern xmlns=\"{}\" version=\"0.7\">\n", NAMESPACE));
+
+        let options = self.options();
+        let information = [("name", &options.title), ("author", &options.author), ("description", &options.notes)];
+        if information.iter().any(|(_, value)| value.is_some()) {
+            out.push_str("  <general-information>\n");
+            for (name, value) in information {
+                if let Some(value) = value {
+                    out.push_str(&format!("    <{}>{}</{}>\n", name, escape(value), name));
+                }
+            }
+            out.push_str("  </general-information>\n");
+        }
+
+        let mut yarns: Vec<char> = self.colors().iter().flatten().flatten().copied().collect();
+        yarns.sort_unstable();
+        yarns.dedup();
+        if !yarns.is_empty() {
+            out.push_str("  <supplies>\n");
+            out.push_str("    <yarn-types>\n");
+            out.push_str("      <yarn-type id=\"yarn-type\">\n");
+            out.push_str("        <yarns>\n");
+            for yarn in yarns {
+                out.push_str(&format!("          <yarn id=\"{}\"/>\n", yarn));
+            }
+            out.push_str("        </yarns>\n");
+            out.push_str("      </yarn-type>\n");
+            out.push_str("    </yarn-types>\n");
+            out.push_str("    <needle-types/>\n");
+            out.push_str("    <accessories/>\n");
+            out.push_str("  </supplies>\n");
+        }
+
+        out.push_str("  <directions>\n");
+        out.push_str(&format!(
+            "    <instruction id=\"chart\" shape=\"{}\">\n",
+            if self.in_round() { "round" } else { "flat" }
+        ));
+
+        for (row, (line, colors)) in self.lines().iter().zip(self.colors()).enumerate() {
+            let side = self.row_side(row);
+            let mut worked: Vec<(Stitch, Option<char>)> = line
+                .iter()
+                .copied()
+                .zip(colors.iter().copied())
+                .filter(|(stitch, _)| *stitch != Stitch::NoStitch)
+                .map(|(stitch, color)| (if side == Side::WS { stitch.wrong_side() } else { stitch }, color))
+                .collect();
+            if side == Side::RS || self.in_round() {
+                worked.reverse();
+            }
+
+            out.push_str(&format!(
+                "      <row number=\"{}\" side=\"{}\">\n",
+                row + self.first_line_number(),
+                if side == Side::RS { "right" } else { "wrong" }
+            ));
+
+            let mut index = 0;
+            while index < worked.len() {
+                let (stitch, color) = worked[index];
+                let (name, attributes) = match directive(stitch) {
+                    Some(directive) => directive,
+                    None => return Err(ParseError::new(ParseErrorType::Unsupported(stitch.to_string()), row)),
+                };
+
+                let mut attribute_text: String = attributes.iter().map(|(name, value)| format!(" {}=\"{}\"", name, value)).collect();
+                if let Some(color) = color {
+                    attribute_text.push_str(&format!(" yarn-ref=\"{}\"", color));
+                }
+
+                if is_counted(name) {
+                    let count = worked[index..].iter().take_while(|worked| **worked == (stitch, color)).count();
+                    out.push_str(&format!("        <{}{}>{}</{}>\n", name, attribute_text, count, name));
+                    index += count;
+                } else {
+                    out.push_str(&format!("        <{}{}/>\n", name, attribute_text));
+                    index += 1;
+                }
+            }
+
+            out.push_str("      </row>\n");
+        }
+
+        out.push_str("    </instruction>\n");
+        out.push_str("  </directions>\n");
+        out.push_str("</pattern>\n");
+        Ok(out)
+    }
+
+    /// Create a new Pattern from a KnitML document.
+    ///
+    /// The rows of every `instruction` in the `directions` are read in order.  Anything in the directions
+    /// that can't be written with this crate's stitches gives an `Unsupported` error, and the line numbers
+    /// of errors are lines of the XML.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - Where to read the KnitML from
+    ///
+    pub fn from_knitml<R: Read>(mut reader: R) -> Result<Pattern, ParseError> {
+        let mut text = String::new();
+        if let Err(error) = reader.read_to_string(&mut text) {
+            return Err(ParseError::new(ParseErrorType::UnableToReadFromReader(Box::new(error)), 0));
+        }
+
+        let document = match Document::parse(&text) {
+            Ok(document) => document,
+            Err(error) => {
+                let position = error.pos();
+                let column = (position.col as usize).saturating_sub(1);
+                return Err(ParseError::new(ParseErrorType::InvalidSyntaxRange(column, column), position.row as usize));
+            }
+        };
+
+        let root = document.root_element();
+        if root.tag_name().name() != "pattern" {
+            return Err(unsupported(root.tag_name().name().to_string(), root));
+        }
+
+        let mut options = Options::default();
+        for information in root.children().filter(|node| node.has_tag_name("general-information")) {
+            for node in information.children().filter(Node::is_element) {
+                let value = node.text().map(|text| text.trim().to_string());
+                match node.tag_name().name() {
+                    "name" => options.title = value,
+                    "author" => options.author = value,
+                    "description" => options.notes = value,
+                    _ => {}
+                }
+            }
+        }
+
+        let mut rows = Vec::new();
+        for directions in root.children().filter(|node| node.has_tag_name("directions")) {
+            collect_rows(directions, &mut options, &mut rows)?;
+        }
+
+        let mut lines = Vec::with_capacity(rows.len());
+        let mut colors = Vec::with_capacity(rows.len());
+        for (row, worked) in rows.into_iter().enumerate() {
+            let side = options.row_side(row);
+            let mut line = VecDeque::with_capacity(worked.len());
+            let mut line_colors = VecDeque::with_capacity(worked.len());
+            for (stitch, color) in worked {
+                line.push_back(if side == Side::WS { stitch.wrong_side() } else { stitch });
+                line_colors.push_back(color);
+            }
+            if side == Side::RS || options.in_round {
+                line.make_contiguous().reverse();
+                line_colors.make_contiguous().reverse();
+            }
+
+            lines.push(line);
+            colors.push(line_colors);
+        }
+
+        Pattern::from_parts(lines, colors, options)
+    }
+}
+
+/// Reads the rows of each instruction under `node`, in the order they are worked.
+fn collect_rows(node: Node, options: &mut Options, rows: &mut Vec<Vec<(Stitch, Option<char>)>>) -> Result<(), ParseError> {
+    for child in node.children().filter(Node::is_element) {
+        match child.tag_name().name() {
+            "instruction-group" => collect_rows(child, options, rows)?,
+            "instruction" => {
+                if rows.is_empty() {
+                    options.in_round = child.attribute("shape") == Some("round");
+                }
+
+                for row in child.children().filter(Node::is_element) {
+                    if !row.has_tag_name("row") {
+                        return Err(unsupported(row.tag_name().name().to_string(), row));
+                    }
+
+                    if rows.is_empty() {
+                        if let Some(number) = row.attribute("number") {
+                            options.first_line_number = number.parse().map_err(|_| unsupported(format!("row number=\"{}\"", number), row))?;
+                        }
+                        if row.attribute("side") == Some("wrong") {
+                            options.starting_side = Side::WS;
+                        }
+                    }
+
+                    let mut worked = Vec::new();
+                    read_directives(row, &mut worked)?;
+                    rows.push(worked);
+                }
+            }
+            name => return Err(unsupported(name.to_string(), child)),
+        }
+    }
+    Ok(())
+}
+
+/// Reads the stitches in a row or repeat onto the end of `worked`.
+fn read_directives(node: Node, worked: &mut Vec<(Stitch, Option<char>)>) -> Result<(), ParseError> {
+    for child in node.children() {
+        if child.is_text() {
+            if child.text().is_some_and(|text| !text.trim().is_empty()) {
+                return Err(unsupported("text".to_string(), child));
+            }
+            continue;
+        }
+        if !child.is_element() {
+            continue;
+        }
+
+        let name = child.tag_name().name();
+        if name == "repeat" {
+            let count = match (child.attribute("until"), child.attribute("value")) {
+                (Some("times"), Some(value)) => value.parse::<usize>().map_err(|_| unsupported(format!("repeat value=\"{}\"", value), child))?,
+                (until, _) => return Err(unsupported(format!("repeat until=\"{}\"", until.unwrap_or_default()), child)),
+            };
+
+            let mut repeated = Vec::new();
+            read_directives(child, &mut repeated)?;
+            for _ in 0..count {
+                worked.extend_from_slice(&repeated);
+            }
+            continue;
+        }
+
+        let stitch = find_stitch(child).ok_or_else(|| {
+            let attributes: String = child
+                .attributes()
+                .map(|attribute| format!(" {}=\"{}\"", attribute.name(), attribute.value()))
+                .collect();
+            unsupported(format!("{}{}", name, attributes), child)
+        })?;
+
+        let color = match child.attribute("yarn-ref") {
+            None => None,
+            Some(yarn) => {
+                let mut chars = yarn.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(color), None) if color.is_ascii_uppercase() => Some(color),
+                    _ => return Err(unsupported(format!("yarn-ref=\"{}\"", yarn), child)),
+                }
+            }
+        };
+
+        let count = if is_counted(name) {
+            match child.text().map(str::trim).unwrap_or_default() {
+                "" => 1,
+                count => count
+                    .parse::<usize>()
+                    .map_err(|_| unsupported(format!("{} count \"{}\"", name, count), child))?,
+            }
+        } else {
+            1
+        };
+
+        for _ in 0..count {
+            worked.push((stitch, color));
+        }
+    }
+    Ok(())
+}
+
+/// Finds the stitch a KnitML element is for.
+///
+/// The element's attributes, other than `yarn-ref`, have to be exactly the ones the stitch is written with, so
+/// something like `<knit loop-to-work="leading">` isn't read as a plain `k`.
+fn find_stitch(node: Node) -> Option<Stitch> {
+    let name = node.tag_name().name();
+    let given = node.attributes().filter(|attribute| attribute.name() != "yarn-ref").count();
+
+    Stitch::ALL
+        .iter()
+        .filter_map(|stitch| directive(*stitch).map(|directive| (*stitch, directive)))
+        .find(|(_, (element, attributes))| {
+            *element == name && attributes.len() == given && attributes.iter().all(|(name, value)| node.attribute(*name) == Some(*value))
+        })
+        .map(|(stitch, _)| stitch)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Stitch::*;
+
+    #[test]
+    fn round_trip() {
+        let input = b"## start_wrong_side\n## first_line=5\n## title=Cables & <lace>\nk, p:A x2, k\nssk, 1lcf, ktbl\nyo, k2tog x2, yo";
+        let pattern = Pattern::new(&input[..]).unwrap();
+
+        let knitml = pattern.to_knitml().unwrap();
+        let read = Pattern::from_knitml(knitml.as_bytes()).unwrap();
+
+        assert_eq!(read.lines(), pattern.lines());
+        assert_eq!(read.colors(), pattern.colors());
+        assert_eq!(read.starting_side(), Side::WS);
+        assert_eq!(read.first_line_number(), 5);
+        assert_eq!(read.options().title.as_deref(), Some("Cables & <lace>"));
+    }
+
+    #[test]
+    fn yarns_declared() {
+        let pattern = Pattern::new(&b"k:B, p:A x2, k:B\nk x4"[..]).unwrap();
+        let knitml = pattern.to_knitml().unwrap();
+
+        assert!(knitml.contains("<yarns>\n          <yarn id=\"A\"/>\n          <yarn id=\"B\"/>\n        </yarns>"));
+        assert!(knitml.find("<supplies>").unwrap() < knitml.find("<directions>").unwrap());
+        assert!(!Pattern::new(&b"k x4"[..]).unwrap().to_knitml().unwrap().contains("<supplies>"));
+    }
+
+    #[test]
+    fn working_order() {
+        let pattern = Pattern::new(&b"k x2, p\nk x2, p"[..]).unwrap();
+        let knitml = pattern.to_knitml().unwrap();
+
+        assert!(knitml.contains("<row number=\"1\" side=\"right\">\n        <purl>1</purl>\n        <knit>2</knit>\n      </row>"));
+        assert!(knitml.contains("<row number=\"2\" side=\"wrong\">\n        <purl>2</purl>\n        <knit>1</knit>\n      </row>"));
+    }
+
+    #[test]
+    fn read_repeats() {
+        let input = r#"<pattern xmlns="http://www.knitml.com/schema/pattern">
+            <directions>
+              <instruction-group id="body">
+                <instruction id="main" shape="round">
+                  <row><repeat until="times" value="2"><knit/><purl>2</purl></repeat><decrease type="ssk" yarn-ref="B"/></row>
+                </instruction>
+              </instruction-group>
+            </directions>
+          </pattern>"#;
+        let pattern = Pattern::from_knitml(input.as_bytes()).unwrap();
+
+        assert!(pattern.in_round());
+        assert_eq!(pattern.lines()[0], VecDeque::from(vec![Ssk, P, P, K, P, P, K]));
+        assert_eq!(pattern.colors()[0][0], Some('B'));
+    }
+
+    #[test]
+    fn unsupported() {
+        let input = "<pattern>\n<directions>\n<instruction>\n<row><knit>2</knit><decrease type=\"sk2p\"/></row>\n</instruction>\n</directions>\n</pattern>";
+        if let Err(parse_error) = Pattern::from_knitml(input.as_bytes()) {
+            assert_eq!(parse_error.line_number(), 4);
+            if let ParseErrorType::Unsupported(ref what) = *parse_error.error_type() {
+                assert_eq!(what, "decrease type=\"sk2p\"");
+            } else {
+                panic!("Wrong error type returned");
+            }
+        } else {
+            panic!("Should not have been valid");
+        }
+
+        assert!(Pattern::from_knitml(&b"<pattern><directions>"[..]).is_err());
+        let leading = r#"<pattern><directions><instruction><row><knit loop-to-work="leading">2</knit></row></instruction></directions></pattern>"#;
+        assert!(Pattern::from_knitml(leading.as_bytes()).is_err());
+        assert!(Pattern::new(&b"k, bobble, k"[..]).unwrap().to_knitml().is_err());
+    }
+}
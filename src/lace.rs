@@ -0,0 +1,272 @@
+//! Checks that each repeat of a lace chart keeps its stitch count.
+//!
+//! Every row is split into its repeat the same way `Pattern::compress` writes it, a row without a repeat is
+//! checked as a whole.  The increases in a repeat, such as `yo`, `kfb` and `ml`, have to be matched by as many
+//! decreases, such as `k2tog`, `ssk` and `p2tog`, or the stitch count drifts by one more stitch every repeat.
+//! The stitches before and after the repeat are checked together, so a `yo` at one edge can be matched by a
+//! decrease at the other.
+
+use std::fmt;
+
+use crate::grid::Cell;
+use crate::repeat::{compress_row, run_length_text};
+use crate::Pattern;
+use crate::Stitch;
+
+/// How one row's repeat changes the stitch count.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct RepeatBalance {
+    /// The row (index into `Pattern::lines`) the repeat is on.
+    pub row: usize,
+
+    /// The first column of the first repeat.
+    pub start_column: usize,
+
+    /// How many columns one repeat covers.
+    pub width: usize,
+
+    /// How many times the repeat is worked, 1 when the row has no repeat.
+    pub count: usize,
+
+    /// The stitches of one repeat, written the same way as in a pattern.
+    pub text: String,
+
+    /// How many stitches one repeat adds.
+    pub increases: usize,
+
+    /// How many stitches one repeat takes away.
+    pub decreases: usize,
+
+    /// How many stitches the stitches before and after the repeat add, 0 when the row has no repeat.
+    pub edge_increases: usize,
+
+    /// How many stitches the stitches before and after the repeat take away, 0 when the row has no repeat.
+    pub edge_decreases: usize,
+
+    /// The stitch that would balance the repeat, or the stitches outside it when only they are unbalanced.
+    pub suggestion: Option<Stitch>,
+}
+
+impl RepeatBalance {
+    /// Returns how many stitches one repeat adds, negative if it takes stitches away.
+    pub fn change(&self) -> isize {
+        self.increases as isize - self.decreases as isize
+    }
+
+    /// Returns how many stitches the stitches before and after the repeat add, negative if they take stitches away.
+    pub fn edge_change(&self) -> isize {
+        self.edge_increases as isize - self.edge_decreases as isize
+    }
+
+    /// Returns how many stitches the whole row adds, negative if it takes stitches away.
+    pub fn row_change(&self) -> isize {
+        self.change() * self.count as isize + self.edge_change()
+    }
+
+    /// Returns if the repeat and the stitches outside it both keep the stitch count.
+    pub fn is_balanced(&self) -> bool {
+        self.increases == self.decreases && self.edge_increases == self.edge_decreases
+    }
+}
+
+impl fmt::Display for RepeatBalance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let change = self.change();
+        let stitches = |count: usize| if count == 1 { "1 stitch".to_string() } else { format!("{} stitches", count) };
+        write!(f, "row {}, column {}: ({}) x{} ", self.row + 1, self.start_column + 1, self.text, self.count)?;
+        match change {
+            0 => write!(f, "is balanced"),
+            change if change > 0 => write!(f, "adds {} per repeat", stitches(change as usize)),
+            change => write!(f, "takes away {} per repeat", stitches(change.unsigned_abs())),
+        }?;
+        match self.edge_change() {
+            0 => Ok(()),
+            change if change > 0 => write!(f, ", the stitches outside the repeat add {}", stitches(change as usize)),
+            change => write!(f, ", the stitches outside the repeat take away {}", stitches(change.unsigned_abs())),
+        }?;
+        match self.suggestion {
+            Some(stitch) => write!(f, ", probably missing a `{}`", stitch),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The result of checking the balance of every row's repeat.
+#[derive(Debug)]
+pub struct LaceReport {
+    repeats: Vec<RepeatBalance>,
+}
+
+impl LaceReport {
+    /// Returns the repeat of every row, in row order.
+    pub fn repeats(&self) -> &Vec<RepeatBalance> {
+        &self.repeats
+    }
+
+    /// Returns the repeats that change the stitch count.
+    pub fn unbalanced(&self) -> Vec<&RepeatBalance> {
+        self.repeats.iter().filter(|repeat| !repeat.is_balanced()).collect()
+    }
+
+    /// Returns if every repeat keeps the stitch count.
+    pub fn is_balanced(&self) -> bool {
+        self.repeats.iter().all(RepeatBalance::is_balanced)
+    }
+}
+
+/// Returns the stitch used the most in the repeat out of the ones matching `wanted`, the first one wins a tie.
+fn most_used(stitches: &[Stitch], wanted: impl Fn(&Stitch) -> bool) -> Option<Stitch> {
+    let mut best: Option<(Stitch, usize)> = None;
+    for stitch in stitches.iter().filter(|stitch| wanted(stitch)) {
+        let count = stitches.iter().filter(|other| *other == stitch).count();
+        if best.is_none_or(|(_, best_count)| count > best_count) {
+            best = Some((*stitch, count));
+        }
+    }
+    best.map(|(stitch, _)| stitch)
+}
+
+/// Returns the stitches in the cells, leaving out the columns covered by a wider stitch.
+fn stitches(cells: &[Cell]) -> Vec<Stitch> {
+    cells
+        .iter()
+        .filter_map(|cell| match cell {
+            Cell::Stitch(stitch, _) => Some(*stitch),
+            Cell::Continued => None,
+        })
+        .collect()
+}
+
+/// Returns how many stitches are added and how many are taken away.
+fn shaping(stitches: &[Stitch]) -> (usize, usize) {
+    let mut increases = 0;
+    let mut decreases = 0;
+    // Binding off takes stitches away on purpose, it isn't something a repeat needs to make up for.
+    for stitch in stitches.iter().filter(|stitch| **stitch != Stitch::Bo) {
+        increases += stitch.produces().saturating_sub(stitch.consumes());
+        decreases += stitch.consumes().saturating_sub(stitch.produces());
+    }
+    (increases, decreases)
+}
+
+/// Returns the stitch that would balance the stitches, if they aren't balanced.
+fn suggestion(stitches: &[Stitch], increases: usize, decreases: usize) -> Option<Stitch> {
+    // Stitches that already use a decrease or increase most likely dropped one more of the same, otherwise go
+    // by what most of them are worked as.
+    if increases > decreases {
+        most_used(stitches, Stitch::is_decrease).or_else(|| {
+            let purls = stitches.iter().filter(|stitch| stitch.is_purl()).count();
+            Some(if purls * 2 > stitches.len() { Stitch::P2Tog } else { Stitch::K2Tog })
+        })
+    } else if decreases > increases {
+        most_used(stitches, |stitch| stitch.produces() > stitch.consumes()).or(Some(Stitch::Yo))
+    } else {
+        None
+    }
+}
+
+/// Works out the balance of one repeat and the stitches on either side of it.
+fn balance(row: usize, start_column: usize, count: usize, cells: &[Cell], edges: &[Cell]) -> RepeatBalance {
+    let stitches = stitches(cells);
+    let (increases, decreases) = shaping(&stitches);
+    let edges = self::stitches(edges);
+    let (edge_increases, edge_decreases) = shaping(&edges);
+
+    RepeatBalance {
+        row,
+        start_column,
+        width: cells.len(),
+        count,
+        text: run_length_text(cells).join(", "),
+        increases,
+        decreases,
+        edge_increases,
+        edge_decreases,
+        suggestion: suggestion(&stitches, increases, decreases).or_else(|| suggestion(&edges, edge_increases, edge_decreases)),
+    }
+}
+
+impl Pattern {
+    /// Checks that the repeat in every row, and the stitches outside it, keep the stitch count.
+    pub fn lace_balance(&self) -> LaceReport {
+        let mut repeats = Vec::new();
+        for (row, cells) in self.cells().iter().enumerate() {
+            // The padding on either side isn't worked, so it's left out.
+            let padding = self.padding(row);
+            let (start, end) = (padding, cells.len() - padding);
+            match compress_row(cells, padding) {
+                (_, Some(repeat)) => {
+                    let unit = &cells[repeat.start_column..repeat.start_column + repeat.width];
+                    let edges: Vec<Cell> = cells[start..repeat.start_column]
+                        .iter()
+                        .chain(&cells[repeat.end_column()..end])
+                        .copied()
+                        .collect();
+                    repeats.push(balance(row, repeat.start_column, repeat.count, unit, &edges));
+                }
+                // Without a repeat the whole row is checked.
+                (_, None) => repeats.push(balance(row, start, 1, &cells[start..end], &[])),
+            }
+        }
+
+        LaceReport { repeats }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn report(input: &str) -> LaceReport {
+        Pattern::new(input.as_bytes()).unwrap().lace_balance()
+    }
+
+    #[test]
+    fn balanced() {
+        let report = report("k, (yo, k2tog, k) x3, k\nk, (p, ssk, yo) x3, k");
+        assert!(report.is_balanced());
+        assert_eq!(report.repeats().len(), 2);
+        assert_eq!(report.repeats()[0].count, 3);
+        assert_eq!(report.repeats()[0].width, 3);
+    }
+
+    #[test]
+    fn unbalanced() {
+        let report = report("(yo, ssk, yo, k) x3\n(k, k2tog, k, ssk) x3");
+        let unbalanced = report.unbalanced();
+        assert_eq!(unbalanced.len(), 2);
+
+        assert_eq!(unbalanced[0].change(), 1);
+        assert_eq!(unbalanced[0].suggestion, Some(Stitch::Ssk));
+        assert_eq!(
+            unbalanced[0].to_string(),
+            "row 1, column 1: (yo, ssk, yo, k) x3 adds 1 stitch per repeat, probably missing a `ssk`"
+        );
+
+        assert_eq!(unbalanced[1].change(), -2);
+        assert_eq!(unbalanced[1].suggestion, Some(Stitch::Yo));
+    }
+
+    #[test]
+    fn outside_repeat() {
+        let gains = report("yo, (k2tog, yo, k) x3, k");
+        assert!(!gains.is_balanced());
+        let row = &gains.repeats()[0];
+        assert_eq!((row.change(), row.edge_change(), row.row_change()), (0, 1, 1));
+        assert_eq!(row.suggestion, Some(Stitch::K2Tog));
+        assert_eq!(
+            row.to_string(),
+            "row 1, column 2: (k2tog, yo, k) x3 is balanced, the stitches outside the repeat add 1 stitch, probably missing a `k2tog`"
+        );
+
+        assert!(report("yo, (k2tog, yo, k) x3, ssk").is_balanced());
+    }
+
+    #[test]
+    fn no_repeat() {
+        let purled = report("p, yo, p x2");
+        assert_eq!(purled.repeats()[0].count, 1);
+        assert_eq!(purled.repeats()[0].suggestion, Some(Stitch::P2Tog));
+        assert!(report("k x4\nbo x4").is_balanced());
+    }
+}
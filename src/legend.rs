@@ -0,0 +1,90 @@
+//! Builds the key for a chart from the stitches a pattern uses.
+//!
+//! Charts show how the right side looks, so each entry says what to work for the symbol on a right side row and on a
+//! wrong side row.
+
+use std::fmt;
+
+use crate::Pattern;
+use crate::Stitch;
+
+/// One stitch in the key of a chart.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LegendEntry {
+    /// The stitch.
+    pub stitch: Stitch,
+
+    /// How the stitch is drawn on a text chart, see `Stitch::symbol`.
+    pub symbol: String,
+
+    /// The abbreviation used in a pattern, such as `k2tog`.
+    pub abbreviation: String,
+
+    /// What to work on a right side row.
+    pub right_side: String,
+
+    /// What to work on a wrong side row.
+    pub wrong_side: String,
+}
+
+impl LegendEntry {
+    /// Creates the entry for a stitch.
+    pub fn new(stitch: Stitch) -> LegendEntry {
+        LegendEntry {
+            stitch,
+            symbol: stitch.symbol(),
+            abbreviation: stitch.to_string(),
+            right_side: stitch.description(),
+            wrong_side: stitch.wrong_side().description(),
+        }
+    }
+
+    /// Returns the full instruction for the stitch, such as `k2tog: knit two stitches together`.
+    pub fn instructions(&self) -> String {
+        format!("{}: {}", self.abbreviation, self.right_side)
+    }
+}
+
+impl fmt::Display for LegendEntry {
+    /// Writes the instruction, with the wrong side as well when it's worked differently.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.instructions())?;
+        if self.wrong_side != self.right_side {
+            write!(f, " on the right side, {} on the wrong side", self.wrong_side)?;
+        }
+        Ok(())
+    }
+}
+
+impl Pattern {
+    /// Returns the key for the chart, one entry for every stitch the pattern uses in the order of `Stitch::ALL`.
+    pub fn legend(&self) -> Vec<LegendEntry> {
+        Stitch::ALL
+            .iter()
+            .filter(|stitch| **stitch != Stitch::NoStitch && self.lines().iter().any(|line| line.contains(stitch)))
+            .map(|stitch| LegendEntry::new(*stitch))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn entries() {
+        let pattern = Pattern::new("k, yo, k2tog, k\np x4".as_bytes()).unwrap();
+        let legend = pattern.legend();
+        let stitches: Vec<Stitch> = legend.iter().map(|entry| entry.stitch).collect();
+        assert_eq!(stitches, vec![Stitch::K, Stitch::P, Stitch::K2Tog, Stitch::Yo]);
+
+        assert_eq!(legend[2].symbol, "/");
+        assert_eq!(legend[2].instructions(), "k2tog: knit two stitches together");
+        assert_eq!(legend[2].wrong_side, "purl two stitches together");
+        assert_eq!(
+            legend[2].to_string(),
+            "k2tog: knit two stitches together on the right side, purl two stitches together on the wrong side"
+        );
+        assert_eq!(legend[3].to_string(), "yo: yarn over");
+    }
+}
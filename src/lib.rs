@@ -2,16 +2,60 @@
 //!
 //! A knitting parser library to simplify working with knitting patterns.
 
+mod builder;
+mod cables;
+mod diff;
+mod edit;
 mod error;
+mod fabric;
+mod format;
+mod gauge;
+mod grading;
+mod grid;
+mod intarsia;
+#[cfg(feature = "knitml")]
+mod knitml;
+mod lace;
+mod legend;
+mod lineage;
+mod lint;
+#[cfg(feature = "lsp")]
+mod lsp;
+mod machine;
 mod parse_line;
 mod pattern;
+mod render;
+mod repeat;
+#[cfg(feature = "serde")]
+mod serialize;
+mod session;
 mod side;
+mod spreadsheet;
+mod statistics;
 mod stitches;
+mod transform;
 mod options;
 mod util;
 
+pub use builder::{Element, PatternBuilder};
+pub use cables::{CableIssue, CableProblem};
+pub use diff::{OptionChange, PatternDiff, RowChange, StitchChange};
 pub use error::{ParseError, ParseErrorType};
+pub use format::{format_pattern, is_formatted};
+pub use gauge::{Dimensions, Gauge, GaugeLength};
+pub use grading::GradingReport;
+pub use intarsia::{ColorRun, Float, IntarsiaReport};
+pub use lace::{LaceReport, RepeatBalance};
+pub use legend::LegendEntry;
+pub use lineage::{Lineage, LiveStitch};
+pub use lint::{Lint, LintConfig, LintWarning};
+#[cfg(feature = "lsp")]
+pub use lsp::LanguageServer;
+pub use machine::MachineChart;
 pub use pattern::Pattern;
+pub use repeat::{CompressedPattern, CompressedRow, HorizontalRepeat, RepeatBox, VerticalRepeat};
+pub use session::{KnittingSession, SessionState, WorkedStitch};
 pub use side::Side;
+pub use statistics::{Statistics, YarnEstimate, YarnFactors};
 pub use stitches::Stitch;
 pub use options::Options;
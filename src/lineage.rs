@@ -0,0 +1,161 @@
+//! Follows each stitch through the rows worked on top of it.
+//!
+//! Every row takes the stitches left on the needle by the row below, in order across the chart, and leaves its own.
+//! A stitch on the needle is linked to the stitches it was worked into, so a decrease has two parents, both halves
+//! of a `kfb` share a parent, a `yo` has none and a cable hands its stitches on with its halves swapped.
+
+use crate::Pattern;
+use crate::Stitch;
+
+/// A stitch left on the needle once a row is worked.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LiveStitch {
+    /// The stitch (index into the row of `Pattern::lines`) that made it.
+    pub stitch: usize,
+
+    /// The column of the chart it sits in.
+    pub column: usize,
+
+    /// The live stitches of the row below (indexes into `Lineage::row`) it was worked into.
+    pub parents: Vec<usize>,
+}
+
+/// How the stitches of every row are linked to the rows below.
+#[derive(Debug)]
+pub struct Lineage {
+    rows: Vec<Vec<LiveStitch>>,
+    mismatched_rows: Vec<usize>,
+}
+
+impl Lineage {
+    /// Returns the stitches on the needle after the given row (index into `Pattern::lines`), in order across the chart.
+    pub fn row(&self, row: usize) -> &[LiveStitch] {
+        self.rows.get(row).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the rows that don't work the same number of stitches the row below left on the needle.
+    ///
+    /// Their stitches are linked as far as they go, any stitches worked past the end have no parents.
+    pub fn mismatched_rows(&self) -> &Vec<usize> {
+        &self.mismatched_rows
+    }
+
+    /// Returns the live stitches of the next row that were worked into the given live stitch.
+    pub fn children(&self, row: usize, index: usize) -> Vec<usize> {
+        (0..self.row(row + 1).len())
+            .filter(|child| self.row(row + 1)[*child].parents.contains(&index))
+            .collect()
+    }
+
+    /// Follows the stitches in a column of a row up through every row after it.
+    ///
+    /// Returns the columns the stitches descended from it are in, one entry per row starting with the given row.
+    /// An entry is empty once every stitch from the column has been bound off.
+    pub fn follow_column(&self, row: usize, column: usize) -> Vec<Vec<usize>> {
+        let mut current: Vec<usize> = (0..self.row(row).len()).filter(|index| self.row(row)[*index].column == column).collect();
+
+        let mut columns = Vec::new();
+        for row in row..self.rows.len() {
+            let mut found: Vec<usize> = current.iter().map(|index| self.rows[row][*index].column).collect();
+            found.dedup();
+            columns.push(found);
+
+            let mut next: Vec<usize> = current.iter().flat_map(|index| self.children(row, *index)).collect();
+            next.sort_unstable();
+            next.dedup();
+            current = next;
+        }
+        columns
+    }
+}
+
+/// Returns the order a stitch hands on the stitches it works, as indexes into the ones it takes.
+fn handed_on(stitch: Stitch, taken: usize) -> Vec<Vec<usize>> {
+    match stitch {
+        // A cable works the second half of its stitches first.
+        _ if stitch.width() > 1 => {
+            let half = taken / 2;
+            (0..taken).map(|index| vec![(index + half) % taken]).collect()
+        }
+        _ if stitch.produces() == taken => (0..taken).map(|index| vec![index]).collect(),
+        _ => (0..stitch.produces()).map(|_| (0..taken).collect()).collect(),
+    }
+}
+
+impl Pattern {
+    /// Links every stitch to the stitches of the row below it was worked into.
+    pub fn lineage(&self) -> Lineage {
+        let mut rows: Vec<Vec<LiveStitch>> = Vec::with_capacity(self.lines().len());
+        let mut mismatched_rows = Vec::new();
+
+        for (row, line) in self.lines().iter().enumerate() {
+            let below = row.checked_sub(1).map_or(0, |below| rows[below].len());
+            let consumed: usize = line.iter().map(Stitch::consumes).sum();
+            if row > 0 && consumed != below {
+                mismatched_rows.push(row);
+            }
+
+            let mut live = Vec::new();
+            let mut next_parent = 0;
+            let mut column = 0;
+            for (index, stitch) in line.iter().enumerate() {
+                // The first row is cast on, so it has nothing below to link to.
+                let taken: Vec<usize> = (next_parent..next_parent + stitch.consumes())
+                    .filter(|parent| row > 0 && *parent < below)
+                    .collect();
+                next_parent += stitch.consumes();
+
+                for (offset, parents) in handed_on(*stitch, stitch.consumes()).into_iter().enumerate() {
+                    live.push(LiveStitch {
+                        stitch: index,
+                        column: column + offset.min(stitch.width().saturating_sub(1)),
+                        parents: parents.into_iter().filter_map(|parent| taken.get(parent).copied()).collect(),
+                    });
+                }
+                column += stitch.width();
+            }
+            rows.push(live);
+        }
+
+        Lineage { rows, mismatched_rows }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn lineage(input: &str) -> Lineage {
+        Pattern::new(input.as_bytes()).unwrap().lineage()
+    }
+
+    fn parents(lineage: &Lineage, row: usize) -> Vec<Vec<usize>> {
+        lineage.row(row).iter().map(|live| live.parents.clone()).collect()
+    }
+
+    #[test]
+    fn increases_and_decreases() {
+        let lineage = lineage("k x4\nk, k2tog, yo, k\nk, kfb, k x2");
+        assert!(lineage.mismatched_rows().is_empty());
+        assert_eq!(parents(&lineage, 0), vec![Vec::<usize>::new(); 4]);
+        assert_eq!(parents(&lineage, 1), vec![vec![0], vec![1, 2], vec![], vec![3]]);
+        assert_eq!(parents(&lineage, 2), vec![vec![0], vec![1], vec![1], vec![2], vec![3]]);
+        assert_eq!(lineage.children(1, 1), vec![1, 2]);
+    }
+
+    #[test]
+    fn cables() {
+        let lineage = lineage("p, k x4, p\np, 2rcb, p\np, k x4, p");
+        assert_eq!(parents(&lineage, 1), vec![vec![0], vec![3], vec![4], vec![1], vec![2], vec![5]]);
+        assert_eq!(lineage.follow_column(0, 1), vec![vec![1], vec![3], vec![3]]);
+        assert_eq!(lineage.follow_column(0, 4), vec![vec![4], vec![2], vec![2]]);
+    }
+
+    #[test]
+    fn bind_off_and_mismatch() {
+        let lineage = lineage("k x4\nbo x2, k x2\nk x4");
+        assert_eq!(lineage.follow_column(0, 0), vec![vec![0], vec![], vec![]]);
+        assert_eq!(lineage.mismatched_rows(), &vec![2]);
+        assert_eq!(parents(&lineage, 2), vec![vec![0], vec![1], vec![], vec![]]);
+    }
+}
@@ -0,0 +1,252 @@
+//! Warnings about things in a pattern that are allowed but are usually a mistake.
+//!
+//! Lints can be turned off in the pattern with `## allow=unbalanced_yo, long_run` and back on with `## warn=`, or
+//! through `LintConfig` when calling `Pattern::lint_with`.
+
+use std::fmt;
+
+use crate::CableIssue;
+use crate::Pattern;
+use crate::Side;
+use crate::Stitch;
+
+/// The checks a lint pass can make.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Lint {
+    /// A `yo` without a decrease in the same row to keep the stitch count.
+    UnbalancedYo,
+
+    /// A cable crossing over columns that have no stitches in the row below.
+    CableOverNoStitch,
+
+    /// A `bo` with stitches still worked above it.
+    BindOffInside,
+
+    /// A wrong side row with a long run of stitches and no purls.
+    LongRun,
+}
+
+impl Lint {
+    /// All the lints, in the order of their codes.
+    pub const ALL: [Lint; 4] = [Lint::UnbalancedYo, Lint::CableOverNoStitch, Lint::BindOffInside, Lint::LongRun];
+
+    /// Returns the code of the lint, such as `L001`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Lint::UnbalancedYo => "L001",
+            Lint::CableOverNoStitch => "L002",
+            Lint::BindOffInside => "L003",
+            Lint::LongRun => "L004",
+        }
+    }
+
+    /// Returns the name the lint is given in `##` options, such as `unbalanced_yo`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Lint::UnbalancedYo => "unbalanced_yo",
+            Lint::CableOverNoStitch => "cable_over_nostitch",
+            Lint::BindOffInside => "bind_off_inside",
+            Lint::LongRun => "long_run",
+        }
+    }
+
+    /// Finds the lint with the given name or code.
+    pub fn from_name(name: &str) -> Option<Lint> {
+        Lint::ALL.iter().find(|lint| lint.name() == name || lint.code() == name).copied()
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Which lints to run and how they are tuned.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct LintConfig {
+    /// The lints that are turned off.
+    pub allowed: Vec<Lint>,
+
+    /// How many stitches a wrong side row can work in a row without a purl before `long_run` warns.
+    pub max_run: usize,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        LintConfig {
+            allowed: Vec::new(),
+            max_run: 20,
+        }
+    }
+}
+
+impl LintConfig {
+    /// Turns a lint off.
+    pub fn allow(&mut self, lint: Lint) {
+        if !self.allowed.contains(&lint) {
+            self.allowed.push(lint);
+        }
+    }
+
+    /// Turns a lint back on.
+    pub fn warn(&mut self, lint: Lint) {
+        self.allowed.retain(|allowed| *allowed != lint);
+    }
+
+    /// Returns if the lint is turned on.
+    pub fn is_enabled(&self, lint: Lint) -> bool {
+        !self.allowed.contains(&lint)
+    }
+}
+
+/// Something a lint found.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LintWarning {
+    /// The lint that found it.
+    pub lint: Lint,
+
+    /// The row (index into `Pattern::lines`) it's on.
+    pub row: usize,
+
+    /// The column of the stitch, counted from the left of the chart starting at 0.
+    pub column: usize,
+
+    /// What's wrong, in words.
+    pub message: String,
+}
+
+impl fmt::Display for LintWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "warning[{}]: {} (row {}, column {})",
+            self.lint.code(),
+            self.message,
+            self.row + 1,
+            self.column + 1
+        )
+    }
+}
+
+/// Returns the stitch in each column of the row, a wide stitch fills all the columns it covers.
+fn columns(line: &[Stitch]) -> Vec<Stitch> {
+    line.iter().flat_map(|stitch| std::iter::repeat_n(*stitch, stitch.width())).collect()
+}
+
+impl Pattern {
+    /// Runs the lints turned on by the pattern's options.
+    pub fn lint(&self) -> Vec<LintWarning> {
+        self.lint_with(&self.options().lints)
+    }
+
+    /// Runs the lints turned on in the given config, ignoring the pattern's own lint options.
+    pub fn lint_with(&self, config: &LintConfig) -> Vec<LintWarning> {
+        let mut warnings = Vec::new();
+        let rows: Vec<Vec<Stitch>> = self.lines().iter().map(|line| line.iter().copied().collect()).collect();
+        let mut warn = |lint: Lint, row: usize, column: usize, message: String| {
+            if config.is_enabled(lint) {
+                warnings.push(LintWarning { lint, row, column, message });
+            }
+        };
+
+        for (row, line) in rows.iter().enumerate() {
+            let above = rows.get(row + 1).map(|above| columns(above));
+
+            let mut decreases = line.iter().filter(|stitch| stitch.is_decrease()).count();
+            let mut run = 0;
+            let mut column = 0;
+            for stitch in line {
+                match stitch {
+                    Stitch::Yo if decreases > 0 => decreases -= 1,
+                    Stitch::Yo => warn(Lint::UnbalancedYo, row, column, "yarn over with no decrease in the row to match it".to_string()),
+                    Stitch::Bo if above.as_ref().is_some_and(|above| above[column] != Stitch::NoStitch) => warn(
+                        Lint::BindOffInside,
+                        row,
+                        column,
+                        "bind off in the middle of the piece, the row above has a stitch over it".to_string(),
+                    ),
+                    _ => {}
+                }
+
+                // Lines are written as the chart symbols, so the wrong side works the opposite stitch.
+                if self.row_side(row) == Side::WS {
+                    if stitch.wrong_side().is_purl() || *stitch == Stitch::NoStitch {
+                        run = 0;
+                    } else {
+                        run += stitch.width();
+                        if run > config.max_run && run - stitch.width() <= config.max_run {
+                            warn(
+                                Lint::LongRun,
+                                row,
+                                column,
+                                format!("more than {} stitches without a purl on a wrong side row", config.max_run),
+                            );
+                        }
+                    }
+                }
+
+                column += stitch.width();
+            }
+        }
+
+        for problem in self.check_cables() {
+            if let CableIssue::CrossesPadding(_) = problem.issue {
+                warn(
+                    Lint::CableOverNoStitch,
+                    problem.row,
+                    problem.column,
+                    format!("`{}` crosses over columns with no stitches in the row below", problem.cable),
+                );
+            }
+        }
+
+        warnings.sort_by_key(|warning| (warning.row, warning.column));
+
+        warnings
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn lint(input: &str) -> Vec<(Lint, usize, usize)> {
+        let pattern = Pattern::new(input.as_bytes()).unwrap();
+        pattern.lint().into_iter().map(|warning| (warning.lint, warning.row, warning.column)).collect()
+    }
+
+    #[test]
+    fn lints() {
+        assert_eq!(lint("k, yo, k2tog, yo, k"), vec![(Lint::UnbalancedYo, 0, 3)]);
+        assert_eq!(lint("k, bo, k\nk, k, k"), vec![(Lint::BindOffInside, 0, 1)]);
+        assert_eq!(lint("k, bo, k\nk, k, k\nbo x3"), vec![(Lint::BindOffInside, 0, 1)]);
+        assert_eq!(lint("k x2\n1rcb, k, k"), vec![(Lint::CableOverNoStitch, 1, 0)]);
+        assert_eq!(lint("k x26\np x21, k x3, p x2"), vec![(Lint::LongRun, 1, 20)]);
+        assert!(lint("## in_round\np x30\np x30").is_empty());
+    }
+
+    #[test]
+    fn flat_stockinette() {
+        assert!(lint("k x30\nk x30\nk x30\nk x30").is_empty());
+    }
+
+    #[test]
+    fn config() {
+        assert!(lint("## allow=unbalanced_yo, L004\nk, yo\np x30").is_empty());
+        assert_eq!(lint("## max_run=3\nk x7\np x2, k, p x4"), vec![(Lint::LongRun, 1, 6)]);
+
+        let pattern = Pattern::new("## allow=unbalanced_yo\nk, yo".as_bytes()).unwrap();
+        let mut config = pattern.options().lints.clone();
+        config.warn(Lint::UnbalancedYo);
+        assert_eq!(pattern.lint_with(&config).len(), 1);
+        assert_eq!(
+            pattern.lint_with(&config)[0].to_string(),
+            "warning[L001]: yarn over with no decrease in the row to match it (row 1, column 2)"
+        );
+    }
+}
@@ -0,0 +1,511 @@
+//! A language server for pattern files, turned on with the `lsp` feature.
+//!
+//! It speaks the Language Server Protocol as JSON-RPC, normally over standard input and output.  Each
+//! document is checked line by line as it changes, so every error in it is reported and not only the
+//! first.  It gives:
+//!
+//! * Diagnostics for lines that can't be read and rows that can't be lined up with the widest row
+//! * Completion for stitch abbreviations, and for option names on `##` lines
+//! * Hover docs for stitches and options
+//! * An inlay hint at the end of each row with the stitches left on the needle
+
+use std::cmp;
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, BufRead, Write};
+
+use serde_json::{json, Value};
+
+use crate::parse_line::{self, SizeSelection};
+use crate::Options;
+use crate::ParseErrorType;
+use crate::Pattern;
+use crate::Stitch;
+
+/// The `##` options with what they do.
+const OPTIONS: [(&str, &str); 13] = [
+    ("in_round", "The pattern is worked in the round."),
+    ("start_wrong_side", "The first row is a wrong side row."),
+    ("first_line=", "The number of the first row, such as `first_line=5`."),
+    ("gauge=", "Stitches and rows over 10 cm or 4 inches, such as `gauge=22x30/10cm`."),
+    ("needle_size=", "The needle size in millimeters, such as `needle_size=4.5mm`."),
+    ("yarn_weight=", "The yarn weight, such as `yarn_weight=worsted`."),
+    ("title=", "The title of the pattern."),
+    ("author=", "Who wrote the pattern."),
+    ("notes=", "Free form notes, can be given more than once."),
+    ("sizes=", "The sizes the pattern is written for, such as `sizes = XS, S, M`."),
+    ("allow=", "Turns lints off, such as `allow=unbalanced_yo, long_run`."),
+    ("warn=", "Turns lints back on, such as `warn=long_run`."),
+    (
+        "max_run=",
+        "How many stitches a wrong side row can have without a purl before `long_run` warns.",
+    ),
+];
+
+/// JSON-RPC error code for a request the server doesn't know.
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// JSON-RPC error code for a message that isn't valid JSON.
+const PARSE_ERROR: i64 = -32700;
+
+/// Returns the UTF-16 column of a byte index in the line, which is how LSP counts columns.
+fn utf16_column(line: &str, byte: usize) -> usize {
+    let mut byte = cmp::min(byte, line.len());
+    while !line.is_char_boundary(byte) {
+        byte -= 1;
+    }
+    line[..byte].encode_utf16().count()
+}
+
+/// Returns the byte index of a UTF-16 column in the line.
+fn byte_index(line: &str, column: usize) -> usize {
+    let mut units = 0;
+    for (index, c) in line.char_indices() {
+        if units >= column {
+            return index;
+        }
+        units += c.len_utf16();
+    }
+    line.len()
+}
+
+fn range(line_index: usize, start: usize, end: usize) -> Value {
+    json!({
+        "start": { "line": line_index, "character": start },
+        "end": { "line": line_index, "character": end },
+    })
+}
+
+/// Makes a diagnostic for an error on the line, covering its syntax range or else the whole line.
+fn diagnostic(line: &str, line_index: usize, error_type: &ParseErrorType) -> Value {
+    let (start, end) = match *error_type {
+        ParseErrorType::InvalidSyntaxRange(start, end) => (utf16_column(line, start), utf16_column(line, end + 1)),
+        _ => (0, utf16_column(line, line.len())),
+    };
+
+    json!({
+        "range": range(line_index, start, end),
+        "severity": 1,
+        "source": "knitting_parse",
+        "message": error_type.describe(),
+    })
+}
+
+/// Checks a whole document, returning its diagnostics and the stitches of each row that could be read.
+///
+/// Rows are given with the index of the line they are on.
+fn check(text: &str) -> (Vec<Value>, Vec<(usize, VecDeque<Stitch>)>) {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut diagnostics = Vec::new();
+
+    // Options are read first so the sizes are known before any stitches are parsed.
+    let mut options = Options::default();
+    for (line_index, line) in lines.iter().enumerate().filter(|(_, line)| line.starts_with("##")) {
+        if let Err(error) = options.parse_options(line, line_index + 1) {
+            diagnostics.push(diagnostic(line, line_index, error.error_type()));
+        }
+    }
+
+    let sizes = SizeSelection {
+        index: 0,
+        count: cmp::max(options.sizes.len(), 1),
+    };
+    let mut rows = Vec::new();
+    for (line_index, line) in lines.iter().enumerate().filter(|(_, line)| !line.starts_with('#')) {
+        match parse_line::parse_colored_stitches(line, line_index + 1, sizes) {
+            Ok((stitches, _)) => rows.push((line_index, stitches)),
+            Err(error) => diagnostics.push(diagnostic(line, line_index, error.error_type())),
+        }
+    }
+
+    let width = |stitches: &VecDeque<Stitch>| stitches.iter().map(Stitch::width).sum::<usize>();
+    let widest = rows.iter().map(|(_, stitches)| width(stitches)).max().unwrap_or(0);
+    for (line_index, stitches) in &rows {
+        let row_width = width(stitches);
+        if !(widest - row_width).is_multiple_of(2) {
+            diagnostics.push(diagnostic(lines[*line_index], *line_index, &ParseErrorType::InvalidStitchCount(row_width)));
+        }
+    }
+
+    // Lints need the whole pattern, so they only run once there are no errors.
+    if diagnostics.is_empty() {
+        if let Ok(pattern) = Pattern::new(text.as_bytes()) {
+            for warning in pattern.lint() {
+                let line_index = rows[warning.row].0;
+                diagnostics.push(json!({
+                    "range": range(line_index, 0, utf16_column(lines[line_index], lines[line_index].len())),
+                    "severity": 2,
+                    "code": warning.lint.code(),
+                    "source": "knitting_parse",
+                    "message": warning.message,
+                }));
+            }
+        }
+    }
+
+    (diagnostics, rows)
+}
+
+/// Finds the stitch written around the byte index, giving the stitch and the byte range it covers.
+fn stitch_at(line: &str, byte: usize) -> Option<(Stitch, usize, usize)> {
+    let is_word = |c: Option<char>| c.is_some_and(|c| c.is_ascii_alphanumeric());
+
+    Stitch::ALL.iter().find_map(|stitch| {
+        let abbreviation = stitch.to_string();
+        line.match_indices(&abbreviation).find_map(|(start, _)| {
+            let end = start + abbreviation.len();
+            let whole = !is_word(line[..start].chars().next_back()) && !is_word(line[end..].chars().next());
+            (whole && start <= byte && byte <= end).then_some((*stitch, start, end))
+        })
+    })
+}
+
+fn stitch_docs(stitch: Stitch) -> String {
+    format!(
+        "**{}**: {}\n\nCovers {} column{} of the chart, works {} stitch{} and leaves {}.",
+        stitch,
+        stitch.description(),
+        stitch.width(),
+        if stitch.width() == 1 { "" } else { "s" },
+        stitch.consumes(),
+        if stitch.consumes() == 1 { "" } else { "es" },
+        stitch.produces()
+    )
+}
+
+/// Talks to an editor over the Language Server Protocol.
+#[derive(Debug, Default)]
+pub struct LanguageServer {
+    documents: HashMap<String, String>,
+    shut_down: bool,
+    exited: bool,
+}
+
+impl LanguageServer {
+    /// Creates a new server with no open documents.
+    pub fn new() -> LanguageServer {
+        LanguageServer::default()
+    }
+
+    /// Returns if the editor asked the server to shut down before it exited.
+    pub fn is_shut_down(&self) -> bool {
+        self.shut_down
+    }
+
+    /// Returns if the editor has told the server to exit.
+    pub fn has_exited(&self) -> bool {
+        self.exited
+    }
+
+    /// Handles one JSON-RPC message, returning the messages to send back.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The request or notification from the editor
+    ///
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default().to_string();
+
+        let result = match method {
+            "initialize" => json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "completionProvider": { "triggerCharacters": ["#", ",", "(", " "] },
+                    "hoverProvider": true,
+                    "inlayHintProvider": true,
+                },
+                "serverInfo": { "name": "knit-lsp", "version": env!("CARGO_PKG_VERSION") },
+            }),
+            "shutdown" => {
+                self.shut_down = true;
+                Value::Null
+            }
+            "exit" => {
+                self.exited = true;
+                return Vec::new();
+            }
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default().to_string();
+                self.documents.insert(uri.clone(), text);
+                return vec![self.publish_diagnostics(&uri)];
+            }
+            "textDocument/didChange" => {
+                // The whole document is sent on each change
+                if let Some(text) = params["contentChanges"]
+                    .as_array()
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str())
+                {
+                    self.documents.insert(uri.clone(), text.to_string());
+                }
+                return vec![self.publish_diagnostics(&uri)];
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                return vec![json!({
+                    "jsonrpc": "2.0",
+                    "method": "textDocument/publishDiagnostics",
+                    "params": { "uri": uri, "diagnostics": [] },
+                })];
+            }
+            "textDocument/completion" => self.completion(&uri, &params["position"]),
+            "textDocument/hover" => self.hover(&uri, &params["position"]),
+            "textDocument/inlayHint" => self.inlay_hints(&uri),
+            _ => {
+                // Notifications the server doesn't use are ignored
+                if message.get("id").is_none() {
+                    return Vec::new();
+                }
+                return vec![json!({
+                    "jsonrpc": "2.0",
+                    "id": message["id"],
+                    "error": { "code": METHOD_NOT_FOUND, "message": format!("unknown method `{}`", method) },
+                })];
+            }
+        };
+
+        match message.get("id") {
+            Some(id) => vec![json!({ "jsonrpc": "2.0", "id": id, "result": result })],
+            None => Vec::new(),
+        }
+    }
+
+    /// Reads messages from `input` and writes the replies to `output` until the editor says to exit or
+    /// `input` ends.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Where to read messages from, normally standard input
+    /// * `output` - Where to write replies to, normally standard output
+    ///
+    pub fn run<R: BufRead, W: Write>(&mut self, mut input: R, mut output: W) -> io::Result<()> {
+        while !self.exited {
+            let mut length = None;
+            loop {
+                let mut header = String::new();
+                if input.read_line(&mut header)? == 0 {
+                    return Ok(());
+                }
+                let header = header.trim_end();
+                if header.is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = header.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        length = value.trim().parse::<usize>().ok();
+                    }
+                }
+            }
+
+            let length = match length {
+                Some(length) => length,
+                None => return Err(io::Error::new(io::ErrorKind::InvalidData, "message without a Content-Length header")),
+            };
+            let mut body = vec![0; length];
+            input.read_exact(&mut body)?;
+
+            let replies = match serde_json::from_slice::<Value>(&body) {
+                Ok(message) => self.handle(&message),
+                Err(error) => vec![json!({
+                    "jsonrpc": "2.0",
+                    "id": Value::Null,
+                    "error": { "code": PARSE_ERROR, "message": error.to_string() },
+                })],
+            };
+            for reply in replies {
+                let text = reply.to_string();
+                write!(output, "Content-Length: {}\r\n\r\n{}", text.len(), text)?;
+            }
+            output.flush()?;
+        }
+        Ok(())
+    }
+
+    fn publish_diagnostics(&self, uri: &str) -> Value {
+        let (diagnostics, _) = check(self.documents.get(uri).map(String::as_str).unwrap_or_default());
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": { "uri": uri, "diagnostics": diagnostics },
+        })
+    }
+
+    /// Returns the line the position is on and the byte index of the position in it.
+    fn line_at<'a>(&'a self, uri: &str, position: &Value) -> Option<(&'a str, usize)> {
+        let line = self.documents.get(uri)?.lines().nth(position["line"].as_u64()? as usize)?;
+        let byte = byte_index(line, position["character"].as_u64()? as usize);
+        Some((line, byte))
+    }
+
+    fn completion(&self, uri: &str, position: &Value) -> Value {
+        let line = self.line_at(uri, position).map_or("", |(line, _)| line);
+
+        let items: Vec<Value> = if line.starts_with("##") {
+            OPTIONS
+                .iter()
+                .map(|(name, docs)| json!({ "label": name, "kind": 10, "documentation": docs }))
+                .collect()
+        } else {
+            Stitch::ALL
+                .iter()
+                .map(|stitch| {
+                    json!({
+                        "label": stitch.to_string(),
+                        "kind": 14,
+                        "detail": stitch.description(),
+                        "documentation": { "kind": "markdown", "value": stitch_docs(*stitch) },
+                    })
+                })
+                .collect()
+        };
+        Value::Array(items)
+    }
+
+    fn hover(&self, uri: &str, position: &Value) -> Value {
+        let (line, byte) = match self.line_at(uri, position) {
+            Some(found) => found,
+            None => return Value::Null,
+        };
+        let line_index = position["line"].as_u64().unwrap_or_default() as usize;
+
+        let found = if line.starts_with("##") {
+            OPTIONS.iter().find_map(|(name, docs)| {
+                let start = line.find(name.trim_end_matches('='))?;
+                let end = start + name.len();
+                (start <= byte && byte <= end).then(|| (format!("**{}**: {}", name.trim_end_matches('='), docs), start, end))
+            })
+        } else if line.starts_with('#') {
+            None
+        } else {
+            stitch_at(line, byte).map(|(stitch, start, end)| (stitch_docs(stitch), start, end))
+        };
+
+        match found {
+            Some((docs, start, end)) => json!({
+                "contents": { "kind": "markdown", "value": docs },
+                "range": range(line_index, utf16_column(line, start), utf16_column(line, end)),
+            }),
+            None => Value::Null,
+        }
+    }
+
+    fn inlay_hints(&self, uri: &str) -> Value {
+        let text = self.documents.get(uri).map(String::as_str).unwrap_or_default();
+        let lines: Vec<&str> = text.lines().collect();
+        let (_, rows) = check(text);
+
+        let hints: Vec<Value> = rows
+            .iter()
+            .map(|(line_index, stitches)| {
+                let count: usize = stitches.iter().map(Stitch::produces).sum();
+                json!({
+                    "position": { "line": line_index, "character": utf16_column(lines[*line_index], lines[*line_index].len()) },
+                    "label": format!("{} sts", count),
+                    "paddingLeft": true,
+                })
+            })
+            .collect();
+        Value::Array(hints)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn open(server: &mut LanguageServer, text: &str) -> Value {
+        let mut replies = server.handle(&json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": { "textDocument": { "uri": "file:///scarf.knit", "text": text } },
+        }));
+        replies.remove(0)
+    }
+
+    fn request(server: &mut LanguageServer, method: &str, line: usize, character: usize) -> Value {
+        let mut replies = server.handle(&json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": {
+                "textDocument": { "uri": "file:///scarf.knit" },
+                "position": { "line": line, "character": character },
+            },
+        }));
+        replies.remove(0)["result"].take()
+    }
+
+    #[test]
+    fn diagnostics() {
+        let mut server = LanguageServer::new();
+        let published = open(&mut server, "## gauge=lots\nk x4\nk, pp\nk x3");
+        let diagnostics = published["params"]["diagnostics"].as_array().unwrap();
+
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!(diagnostics[0]["range"]["start"]["line"], 0);
+        assert_eq!(diagnostics[1]["range"], range(2, 4, 5));
+        assert_eq!(diagnostics[1]["message"], "invalid syntax");
+        assert_eq!(diagnostics[2]["range"], range(3, 0, 4));
+
+        let published = open(&mut server, "# lace\nk, yo, k");
+        let diagnostics = published["params"]["diagnostics"].as_array().unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0]["severity"], 2);
+        assert_eq!(diagnostics[0]["code"], "L001");
+        assert_eq!(diagnostics[0]["range"], range(1, 0, 8));
+    }
+
+    #[test]
+    fn completion_and_hover() {
+        let mut server = LanguageServer::new();
+        open(&mut server, "## in_round\nk2tog, sl kwise x2");
+
+        let options = request(&mut server, "textDocument/completion", 0, 3);
+        assert!(options.as_array().unwrap().iter().any(|item| item["label"] == "gauge="));
+        let stitches = request(&mut server, "textDocument/completion", 1, 0);
+        assert_eq!(stitches.as_array().unwrap().len(), Stitch::ALL.len());
+
+        let hover = request(&mut server, "textDocument/hover", 1, 10);
+        assert_eq!(hover["range"], range(1, 7, 15));
+        assert!(hover["contents"]["value"]
+            .as_str()
+            .unwrap()
+            .starts_with("**sl kwise**: slip one stitch knitwise"));
+        assert_eq!(request(&mut server, "textDocument/hover", 0, 4)["range"], range(0, 3, 11));
+    }
+
+    #[test]
+    fn inlay_hints() {
+        let mut server = LanguageServer::new();
+        open(&mut server, "k x4\n# decrease\nk, k2tog, k");
+
+        let hints = request(&mut server, "textDocument/inlayHint", 0, 0);
+        assert_eq!(hints[0]["label"], "4 sts");
+        assert_eq!(hints[1]["position"]["line"], 2);
+        assert_eq!(hints[1]["label"], "3 sts");
+    }
+
+    #[test]
+    fn framing() {
+        let messages = [
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} }),
+            json!({ "jsonrpc": "2.0", "id": 2, "method": "shutdown" }),
+            json!({ "jsonrpc": "2.0", "method": "exit" }),
+        ];
+        let input: String = messages
+            .iter()
+            .map(|message| format!("Content-Length: {}\r\n\r\n{}", message.to_string().len(), message))
+            .collect();
+
+        let mut server = LanguageServer::new();
+        let mut output = Vec::new();
+        server.run(input.as_bytes(), &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("Content-Length: "));
+        assert!(output.contains("\"hoverProvider\":true"));
+        assert!(server.is_shut_down());
+        assert!(server.has_exited());
+    }
+}
@@ -0,0 +1,268 @@
+//! Needle selections for knitting machines, written as bitmaps or punchcards.
+
+use crate::ParseError;
+use crate::ParseErrorType;
+use crate::Pattern;
+use crate::Stitch;
+
+/// How many stitches a standard punchcard covers.
+const PUNCHCARD_WIDTH: usize = 24;
+
+/// The colors used for the bitmap palette, in palette order.  Colors past the end are made up from the index.
+const PALETTE: [[u8; 3]; 8] = [
+    [255, 255, 255],
+    [0, 0, 0],
+    [220, 40, 40],
+    [40, 80, 220],
+    [40, 160, 60],
+    [240, 200, 40],
+    [150, 60, 180],
+    [240, 130, 30],
+];
+
+/// The needle selections for each row of a pattern.
+///
+/// Each needle holds a palette index.  A pattern with colors uses `0` for the main color and `1` and up for
+/// `colors()` in order.  A pattern without colors uses `0` for knit stitches and `1` for purl stitches.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct MachineChart {
+    width: usize,
+    rows: Vec<Vec<u8>>,
+    colors: Vec<char>,
+}
+
+impl MachineChart {
+    /// Returns how many needles wide the chart is.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the palette index for each needle, with the first row knit first.
+    pub fn rows(&self) -> &Vec<Vec<u8>> {
+        &self.rows
+    }
+
+    /// Returns the contrast colors, `colors()[i]` is palette index `i + 1`.
+    pub fn colors(&self) -> &Vec<char> {
+        &self.colors
+    }
+
+    /// Returns if the needle is selected on the row.
+    pub fn is_selected(&self, row: usize, needle: usize) -> bool {
+        self.rows.get(row).and_then(|row| row.get(needle)).is_some_and(|index| *index != 0)
+    }
+
+    /// Writes the selections as a plain PBM image, with the last row at the top the same as a chart.
+    ///
+    /// Any needle that isn't palette index `0` is black.
+    pub fn to_pbm(&self) -> String {
+        let mut out = format!("P1\n{} {}\n", self.width, self.rows.len());
+        for row in self.rows.iter().rev() {
+            let pixels: Vec<&str> = row.iter().map(|index| if *index == 0 { "0" } else { "1" }).collect();
+            out.push_str(&pixels.join(" "));
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Writes the chart as a PNG image with a color palette, with the last row at the top the same as a chart.
+    ///
+    /// A PNG can't be empty, so a chart with no needles or no rows gives an `InvalidStitchCount` error.
+    pub fn to_png(&self) -> Result<Vec<u8>, ParseError> {
+        if self.width == 0 || self.rows.is_empty() {
+            return Err(ParseError::new(ParseErrorType::InvalidStitchCount(self.width), 0));
+        }
+
+        let mut out = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&(self.width as u32).to_be_bytes());
+        header.extend_from_slice(&(self.rows.len() as u32).to_be_bytes());
+        // 8 bit palette indexes, default compression, filtering and no interlace
+        header.extend_from_slice(&[8, 3, 0, 0, 0]);
+        png_chunk(&mut out, b"IHDR", &header);
+
+        let palette: Vec<u8> = (0..self.colors.len().max(1) + 1).flat_map(palette_color).collect();
+        png_chunk(&mut out, b"PLTE", &palette);
+
+        let mut scanlines = Vec::with_capacity(self.rows.len() * (self.width + 1));
+        for row in self.rows.iter().rev() {
+            // No filter
+            scanlines.push(0);
+            scanlines.extend_from_slice(row);
+        }
+        png_chunk(&mut out, b"IDAT", &zlib_stored(&scanlines));
+        png_chunk(&mut out, b"IEND", &[]);
+        Ok(out)
+    }
+
+    /// Writes the selections as a punchcard for a 24 stitch machine, one line per card row.
+    ///
+    /// A punched hole is `X` and the rest are `.`.  The first row knit is the first line and the chart is
+    /// repeated across the card, so its width has to divide 24.  A chart with more than one contrast color
+    /// can't be punched.
+    pub fn to_punchcard(&self) -> Result<String, ParseError> {
+        if self.width == 0 || !PUNCHCARD_WIDTH.is_multiple_of(self.width) {
+            return Err(ParseError::new(ParseErrorType::InvalidStitchCount(self.width), 0));
+        }
+        if self.colors.len() > 1 {
+            return Err(ParseError::new(ParseErrorType::Unsupported("more than two colors".to_string()), 0));
+        }
+
+        let mut out = String::with_capacity(self.rows.len() * (PUNCHCARD_WIDTH + 1));
+        for row in &self.rows {
+            for needle in 0..PUNCHCARD_WIDTH {
+                out.push(if row[needle % self.width] == 0 { '.' } else { 'X' });
+            }
+            out.push('\n');
+        }
+        Ok(out)
+    }
+}
+
+fn palette_color(index: usize) -> [u8; 3] {
+    match PALETTE.get(index) {
+        Some(color) => *color,
+        None => [(index * 73 % 256) as u8, (index * 151 % 256) as u8, (index * 199 % 256) as u8],
+    }
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in bytes {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+fn png_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Wraps the data in a zlib stream without compressing it.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let mut blocks = data.chunks(u16::MAX as usize).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        out.push(if blocks.peek().is_none() { 1 } else { 0 });
+        let length = block.len() as u16;
+        out.extend_from_slice(&length.to_le_bytes());
+        out.extend_from_slice(&(!length).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+impl Pattern {
+    /// Returns the needle selections to knit the pattern on a machine.
+    ///
+    /// Each needle works one stitch into one stitch, so cables, increases, decreases, yarn overs, bind offs,
+    /// bobbles and beads can't be charted and give an `Unsupported` error on the row they are in.  Any
+    /// `nostitch` padding is a needle out of work and left unselected.
+    pub fn machine_chart(&self) -> Result<MachineChart, ParseError> {
+        let mut colors: Vec<char> = self.colors().iter().flatten().flatten().copied().collect();
+        colors.sort_unstable();
+        colors.dedup();
+
+        let mut rows = Vec::with_capacity(self.lines().len());
+        for (row, (line, line_colors)) in self.lines().iter().zip(self.colors()).enumerate() {
+            let mut needles = Vec::with_capacity(self.pattern_width());
+            for (stitch, color) in line.iter().zip(line_colors) {
+                let one_to_one = stitch.consumes() == 1 && stitch.produces() == 1;
+                if *stitch != Stitch::NoStitch && (!one_to_one || *stitch == Stitch::Bobble || *stitch == Stitch::Bead) {
+                    return Err(ParseError::new(ParseErrorType::Unsupported(stitch.to_string()), row));
+                }
+
+                let index = if *stitch == Stitch::NoStitch {
+                    0
+                } else if colors.is_empty() {
+                    stitch.is_purl() as usize
+                } else {
+                    // This unwrap should be fine, every color was added to colors above
+                    color.map(|color| colors.binary_search(&color).unwrap() + 1).unwrap_or(0)
+                };
+                needles.push(index as u8);
+            }
+            rows.push(needles);
+        }
+
+        Ok(MachineChart {
+            width: self.pattern_width(),
+            rows,
+            colors,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn knit_purl() {
+        let chart = Pattern::new(&b"k, p, k, p\np, ktbl, ptbl, sl kwise"[..]).unwrap().machine_chart().unwrap();
+
+        assert_eq!(chart.rows(), &vec![vec![0, 1, 0, 1], vec![1, 0, 1, 0]]);
+        assert_eq!(chart.to_pbm(), "P1\n4 2\n1 0 1 0\n0 1 0 1\n");
+        assert_eq!(chart.to_punchcard().unwrap().lines().next().unwrap(), ".X".repeat(12));
+    }
+
+    #[test]
+    fn colorwork() {
+        let chart = Pattern::new(&b"k:B, k, k:A\nk, k:A, k"[..]).unwrap().machine_chart().unwrap();
+
+        assert_eq!(chart.colors(), &vec!['A', 'B']);
+        assert_eq!(chart.rows()[0], vec![2, 0, 1]);
+        assert!(chart.is_selected(1, 1));
+        assert!(chart.to_punchcard().is_err());
+
+        let png = chart.to_png().unwrap();
+        assert_eq!(&png[..8], &[0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n']);
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[png.len() - 8..png.len() - 4], b"IEND");
+        // The IEND crc is always the same
+        assert_eq!(&png[png.len() - 4..], &[0xae, 0x42, 0x60, 0x82]);
+    }
+
+    #[test]
+    fn unworkable() {
+        if let Err(parse_error) = Pattern::new(&b"k x4\nk, 1lcf, k"[..]).unwrap().machine_chart() {
+            assert_eq!(parse_error.line_number(), 1);
+            if let ParseErrorType::Unsupported(ref what) = *parse_error.error_type() {
+                assert_eq!(what, "1lcf");
+            } else {
+                panic!("Wrong error type returned");
+            }
+        } else {
+            panic!("Should not have been valid");
+        }
+
+        assert!(Pattern::new(&b"k, bobble, k"[..]).unwrap().machine_chart().is_err());
+        for shaping in ["k, k2tog, k", "k, yo, k", "k, bo, k", "k, ml, k", "k, kfb, k"] {
+            assert!(Pattern::new(shaping.as_bytes()).unwrap().machine_chart().is_err(), "{}", shaping);
+        }
+        assert!(Pattern::new(&b""[..]).unwrap().machine_chart().unwrap().to_png().is_err());
+        assert!(Pattern::new(&b"k x5"[..]).unwrap().machine_chart().unwrap().to_punchcard().is_err());
+    }
+}
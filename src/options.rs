@@ -1,20 +1,27 @@
 //! Additional options that can be set on a pattern.
 
 use nom::{
-    bytes::complete::tag,
-    character::complete::{digit1, line_ending, space0},
-    combinator::opt,
+    branch::alt,
+    bytes::complete::{is_not, tag},
+    character::complete::{char, digit1, line_ending, not_line_ending, space0},
+    combinator::{map_opt, opt, recognize, value, verify},
+    multi::separated_list1,
+    sequence::{delimited, pair},
     IResult,
 };
 
 use std::str;
 
 use crate::Side;
+use crate::{Gauge, GaugeLength};
+use crate::{Lint, LintConfig};
 use crate::ParseError;
 use crate::util::into_parse_error;
 
 /// The options for a pattern.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct Options {
     /// What the first line should be labeled.
     pub first_line_number: usize,
@@ -24,6 +31,30 @@ pub struct Options {
 
     /// If the pattern is in the round.
     pub in_round: bool,
+
+    /// The gauge the pattern is worked at.
+    pub gauge: Option<Gauge>,
+
+    /// The needle size in millimeters.
+    pub needle_size: Option<f64>,
+
+    /// The yarn weight, for example `worsted`.
+    pub yarn_weight: Option<String>,
+
+    /// The title of the pattern.
+    pub title: Option<String>,
+
+    /// Who wrote the pattern.
+    pub author: Option<String>,
+
+    /// Free form notes, each `notes=` line is kept on its own line.
+    pub notes: Option<String>,
+
+    /// The names of the sizes the pattern is written for, empty if there is only one size.
+    pub sizes: Vec<String>,
+
+    /// Which lints `Pattern::lint` runs, set with `allow=`, `warn=` and `max_run=`.
+    pub lints: LintConfig,
 }
 
 impl Default for Options {
@@ -35,6 +66,14 @@ impl Default for Options {
             first_line_number,
             starting_side,
             in_round,
+            gauge: None,
+            needle_size: None,
+            yarn_weight: None,
+            title: None,
+            author: None,
+            notes: None,
+            sizes: Vec::new(),
+            lints: LintConfig::default(),
         }
     }
 }
@@ -91,12 +130,138 @@ impl Options {
         }
     }
 
+    fn parse_gauge<'a>(&mut self, line: &'a str) -> IResult<&'a str, ()> {
+        let (line, gauge) = opt(tag("gauge="))(line)?;
+        match gauge {
+            Some(_) => {
+                let (line, stitches) = positive_decimal(line)?;
+                let (line, _) = char('x')(line)?;
+                let (line, rows) = positive_decimal(line)?;
+                let (line, _) = char('/')(line)?;
+                let (line, length) = alt((value(GaugeLength::TenCm, tag("10cm")), value(GaugeLength::FourInches, tag("4in"))))(line)?;
+                if !line.is_empty() {
+                    line_ending(line)?;
+                }
+
+                self.gauge = Some(Gauge { stitches, rows, length });
+                Ok((line, ()))
+            }
+            None => Ok((line, ())),
+        }
+    }
+
+    fn parse_needle_size<'a>(&mut self, line: &'a str) -> IResult<&'a str, ()> {
+        let (line, needle_size) = opt(tag("needle_size="))(line)?;
+        match needle_size {
+            Some(_) => {
+                let (line, needle_size) = positive_decimal(line)?;
+                let (line, _) = opt(tag("mm"))(line)?;
+                if !line.is_empty() {
+                    line_ending(line)?;
+                }
+
+                self.needle_size = Some(needle_size);
+                Ok((line, ()))
+            }
+            None => Ok((line, ())),
+        }
+    }
+
+    fn parse_text<'a>(line: &'a str, name: &'static str) -> IResult<&'a str, Option<String>> {
+        let (line, found) = opt(tag(name))(line)?;
+        match found {
+            Some(_) => {
+                let (line, text) = not_line_ending(line)?;
+                Ok((line, Some(text.trim().to_string())))
+            }
+            None => Ok((line, None)),
+        }
+    }
+
+    fn parse_metadata<'a>(&mut self, line: &'a str) -> IResult<&'a str, ()> {
+        let (line, yarn_weight) = Options::parse_text(line, "yarn_weight=")?;
+        let (line, title) = Options::parse_text(line, "title=")?;
+        let (line, author) = Options::parse_text(line, "author=")?;
+        let (line, notes) = Options::parse_text(line, "notes=")?;
+
+        if yarn_weight.is_some() {
+            self.yarn_weight = yarn_weight;
+        }
+        if title.is_some() {
+            self.title = title;
+        }
+        if author.is_some() {
+            self.author = author;
+        }
+        if let Some(notes) = notes {
+            self.notes = match self.notes.take() {
+                Some(existing) => Some(format!("{}\n{}", existing, notes)),
+                None => Some(notes),
+            };
+        }
+
+        Ok((line, ()))
+    }
+
+    fn parse_sizes<'a>(&mut self, line: &'a str) -> IResult<&'a str, ()> {
+        let (line, sizes) = opt(tag("sizes"))(line)?;
+        match sizes {
+            Some(_) => {
+                let (line, _) = space0(line)?;
+                let (line, _) = char('=')(line)?;
+                let (line, sizes) = separated_list1(char(','), delimited(space0, is_not(", \t\r\n"), space0))(line)?;
+                if !line.is_empty() {
+                    line_ending(line)?;
+                }
+
+                self.sizes = sizes.into_iter().map(String::from).collect();
+                Ok((line, ()))
+            }
+            None => Ok((line, ())),
+        }
+    }
+
+    fn parse_lints<'a>(&mut self, line: &'a str) -> IResult<&'a str, ()> {
+        let (line, setting) = opt(alt((tag("allow="), tag("warn="), tag("max_run="))))(line)?;
+        let line = match setting {
+            Some("max_run=") => {
+                let (line, max_run) = digit1(line)?;
+
+                // This unwrap should be fine, we check it's valid above.
+                self.lints.max_run = max_run.parse::<usize>().unwrap();
+                line
+            }
+            Some(setting) => {
+                let (line, lints) = separated_list1(char(','), delimited(space0, map_opt(is_not(", \t\r\n"), Lint::from_name), space0))(line)?;
+                for lint in lints {
+                    if setting == "allow=" {
+                        self.lints.allow(lint);
+                    } else {
+                        self.lints.warn(lint);
+                    }
+                }
+                line
+            }
+            None => line,
+        };
+        if setting.is_some() && !line.is_empty() {
+            line_ending(line)?;
+        }
+
+        Ok((line, ()))
+    }
+
     fn internal_parse<'a>(&mut self, line: &'a str) -> IResult<&'a str, ()> {
         let (line, _) = space0(line)?;
 
         let (line, _) = self.parse_in_round(line)?;
         let (line, _) = self.parse_first_line(line)?;
         let (line, _) = self.parse_start_wrong(line)?;
+        let (line, _) = self.parse_gauge(line)?;
+        let (line, _) = self.parse_needle_size(line)?;
+        let (line, _) = self.parse_metadata(line)?;
+        let (line, _) = self.parse_sizes(line)?;
+        let (line, _) = self.parse_lints(line)?;
 
         // make sure we fully parsed the line
         if !line.is_empty() {
@@ -122,6 +287,68 @@ impl Options {
             }
         }
     }
+
+    /// Returns the side the given row (counted from 0) is worked on.
+    pub(crate) fn row_side(&self, row: usize) -> Side {
+        if row.is_multiple_of(2) {
+            self.starting_side
+        } else {
+            self.starting_side.switch(self.in_round)
+        }
+    }
+
+    /// Returns the `##` lines that set these options, leaving out any that are the default.
+    pub(crate) fn to_lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        if self.in_round {
+            lines.push("## in_round".to_string());
+        }
+        if self.first_line_number != 1 {
+            lines.push(format!("## first_line={}", self.first_line_number));
+        }
+        if self.starting_side == Side::WS {
+            lines.push("## start_wrong_side".to_string());
+        }
+        if let Some(gauge) = self.gauge {
+            lines.push(format!("## gauge={}", gauge));
+        }
+        if let Some(needle_size) = self.needle_size {
+            lines.push(format!("## needle_size={}mm", needle_size));
+        }
+        let text = [("yarn_weight", &self.yarn_weight), ("title", &self.title), ("author", &self.author)];
+        for (name, value) in text {
+            if let Some(value) = value {
+                lines.push(format!("## {}={}", name, value));
+            }
+        }
+        if let Some(notes) = &self.notes {
+            lines.extend(notes.lines().map(|note| format!("## notes={}", note)));
+        }
+        if !self.sizes.is_empty() {
+            lines.push(format!("## sizes={}", self.sizes.join(", ")));
+        }
+        if !self.lints.allowed.is_empty() {
+            let allowed: Vec<&str> = self.lints.allowed.iter().map(Lint::name).collect();
+            lines.push(format!("## allow={}", allowed.join(", ")));
+        }
+        if self.lints.max_run != LintConfig::default().max_run {
+            lines.push(format!("## max_run={}", self.lints.max_run));
+        }
+        lines
+    }
+}
+
+/// Parses a number that may have a decimal part, such as `22` or `4.5`.
+fn decimal(line: &str) -> IResult<&str, f64> {
+    let (line, number) = recognize(pair(digit1, opt(pair(char('.'), digit1))))(line)?;
+
+    // This unwrap should be fine, we only allow digits and a single '.' above.
+    Ok((line, number.parse::<f64>().unwrap()))
+}
+
+/// A decimal number greater than zero, gauges and needle sizes are divided by so they can't be zero.
+fn positive_decimal(line: &str) -> IResult<&str, f64> {
+    verify(decimal, |number: &f64| *number > 0.0)(line)
 }
 
 #[cfg(test)]
@@ -148,4 +375,74 @@ mod test {
         options.parse_options("## start_wrong_side", 0).unwrap();
         assert_eq!(options.starting_side, Side::WS);
     }
+
+    #[test]
+    fn gauge() {
+        let mut options = Options::default();
+        options.parse_options("## gauge=22x30/10cm", 0).unwrap();
+        assert_eq!(
+            options.gauge,
+            Some(Gauge {
+                stitches: 22.0,
+                rows: 30.0,
+                length: GaugeLength::TenCm,
+            })
+        );
+
+        options.parse_options("## gauge=18.5x24/4in", 0).unwrap();
+        assert_eq!(options.gauge.unwrap().length, GaugeLength::FourInches);
+        assert_eq!(options.gauge.unwrap().stitches, 18.5);
+    }
+
+    #[test]
+    fn needle_and_yarn() {
+        let mut options = Options::default();
+        options.parse_options("## needle_size=4.5mm", 0).unwrap();
+        options.parse_options("## yarn_weight=worsted", 0).unwrap();
+        assert_eq!(options.needle_size, Some(4.5));
+        assert_eq!(options.yarn_weight.as_deref(), Some("worsted"));
+    }
+
+    #[test]
+    fn metadata() {
+        let mut options = Options::default();
+        options.parse_options("## title= Seed Stitch Scarf ", 0).unwrap();
+        options.parse_options("## author=Jane Doe", 0).unwrap();
+        options.parse_options("## notes=Block lightly.", 0).unwrap();
+        options.parse_options("## notes=Weave in ends.", 0).unwrap();
+        assert_eq!(options.title.as_deref(), Some("Seed Stitch Scarf"));
+        assert_eq!(options.author.as_deref(), Some("Jane Doe"));
+        assert_eq!(options.notes.as_deref(), Some("Block lightly.\nWeave in ends."));
+    }
+
+    #[test]
+    fn bad_gauge() {
+        let mut options = Options::default();
+        assert!(options.parse_options("## gauge=22/10cm", 0).is_err());
+        assert!(options.parse_options("## gauge=0x30/10cm", 0).is_err());
+        assert!(options.parse_options("## gauge=22x0.0/10cm", 0).is_err());
+        assert!(options.parse_options("## gauge=-22x30/10cm", 0).is_err());
+        assert!(options.parse_options("## needle_size=0mm", 0).is_err());
+        assert!(options.gauge.is_none());
+        assert!(options.needle_size.is_none());
+    }
+
+    #[test]
+    fn sizes() {
+        let mut options = Options::default();
+        options.parse_options("## sizes = XS, S, M,L", 0).unwrap();
+        assert_eq!(options.sizes, vec!["XS", "S", "M", "L"]);
+        assert!(options.parse_options("## sizes = XS,,S", 0).is_err());
+    }
+
+    #[test]
+    fn lints() {
+        let mut options = Options::default();
+        options.parse_options("## allow=unbalanced_yo, L004", 0).unwrap();
+        options.parse_options("## warn=long_run", 0).unwrap();
+        options.parse_options("## max_run=12", 0).unwrap();
+        assert_eq!(options.lints.allowed, vec![Lint::UnbalancedYo]);
+        assert_eq!(options.lints.max_run, 12);
+        assert!(options.parse_options("## allow=everything", 0).is_err());
+    }
 }
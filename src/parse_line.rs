@@ -3,10 +3,11 @@
 use nom::{
     branch::alt,
     bytes::complete::tag,
-    character::complete::{char, digit1, space0},
-    combinator::opt,
+    character::complete::{char, digit1, satisfy, space0},
+    combinator::{map_res, opt},
+    error::{Error, ErrorKind},
     multi::separated_list1,
-    sequence::preceded,
+    sequence::{delimited, preceded},
     IResult,
 };
 
@@ -17,6 +18,25 @@ use crate::util::into_parse_error;
 use crate::ParseError;
 use crate::Stitch;
 
+/// A stitch along with the color it's worked in, if one was given.
+type ColoredStitch = (Stitch, Option<char>);
+
+/// Which size to pick when a count gives a number per size, such as `x10 (12, 14, 16)`.
+#[derive(Debug, Copy, Clone)]
+pub struct SizeSelection {
+    /// The index of the size to pick.
+    pub index: usize,
+
+    /// How many sizes the pattern has, counts with alternatives must give this many numbers.
+    pub count: usize,
+}
+
+impl Default for SizeSelection {
+    fn default() -> Self {
+        SizeSelection { index: 0, count: 1 }
+    }
+}
+
 fn stitch<'a>(stitch_str: &'a str, stitch_type: Stitch) -> impl Fn(&'a str) -> IResult<&'a str, Stitch> {
     move |line: &'a str| {
         let res: IResult<_, _> = match tag(stitch_str)(line) {
@@ -28,48 +48,79 @@ fn stitch<'a>(stitch_str: &'a str, stitch_type: Stitch) -> impl Fn(&'a str) -> I
     }
 }
 
-fn multiplier(line: &str) -> IResult<&str, usize> {
-    let (line, _) = tag("x")(line)?;
-    let (line, mult) = digit1(line)?;
+fn number(line: &str) -> IResult<&str, usize> {
+    map_res(digit1, str::parse::<usize>)(line)
+}
 
-    // This unwrap should be fine, as we only allow valid chars from above.
-    Ok((line, mult.parse::<usize>().unwrap()))
+/// How many times something is repeated as it was written, with the numbers for the other sizes if there are any.
+pub(crate) struct Count {
+    pub(crate) first: usize,
+    pub(crate) alternatives: Vec<usize>,
 }
 
-fn padded_group(line: &str) -> IResult<&str, std::vec::Vec<Stitch>> {
-    let (line, _) = space0(line)?;
-    let (line, _) = tag("(")(line)?;
-    let (line, vecs) = separated_list1(char(','), alt((padded_group, padded_stitch)))(line)?;
-    let (line, _) = space0(line)?;
-    let (line, _) = tag(")")(line)?;
-    let (line, _) = space0(line)?;
-    let (line, mult) = opt(multiplier)(line)?;
-
-    let mut vec = std::vec::Vec::new();
-    match mult {
-        Some(val) => {
-            for _ in 0..val {
-                for vec_to_append in &vecs {
-                    vec.extend(vec_to_append);
-                }
-            }
+impl Count {
+    /// Returns the number for the selected size, or `None` if the count doesn't give one for every size.
+    fn pick(&self, sizes: SizeSelection) -> Option<usize> {
+        if self.alternatives.is_empty() {
+            return Some(self.first);
         }
-        None => {
-            for vec_to_append in &vecs {
-                vec.extend(vec_to_append);
-            }
+
+        // Every size needs its own number, the first is outside the parenthesis.
+        if self.alternatives.len() + 1 != sizes.count {
+            return None;
+        }
+
+        match sizes.index {
+            0 => Some(self.first),
+            index => Some(self.alternatives[index - 1]),
         }
     }
+}
 
-    Ok((line, vec))
+/// A part of a stitch line as it was written, before any counts are expanded.
+pub(crate) enum Item {
+    Stitch(Stitch, Option<char>, Option<Count>),
+    Group(Vec<Item>, Option<Count>),
 }
 
-fn padded_stitch(line: &str) -> IResult<&str, std::vec::Vec<Stitch>> {
+/// Parses a count, such as `x3` or `x10 (12, 14)`.
+pub(crate) fn multiplier(line: &str) -> IResult<&str, Count> {
+    let (line, _) = tag("x")(line)?;
+    let (line, first) = number(line)?;
+    let (line, alternatives) = opt(preceded(
+        space0,
+        delimited(char('('), separated_list1(char(','), delimited(space0, number, space0)), char(')')),
+    ))(line)?;
+
+    Ok((
+        line,
+        Count {
+            first,
+            alternatives: alternatives.unwrap_or_default(),
+        },
+    ))
+}
+
+fn color(line: &str) -> IResult<&str, char> {
+    preceded(char(':'), satisfy(|c| c.is_ascii_uppercase()))(line)
+}
+
+fn group_item(line: &str) -> IResult<&str, Item> {
+    let (line, _) = space0(line)?;
+    let (line, _) = tag("(")(line)?;
+    let (line, items) = separated_list1(char(','), item)(line)?;
+    let (line, _) = space0(line)?;
+    let (line, _) = tag(")")(line)?;
     let (line, _) = space0(line)?;
+    let (line, count) = opt(multiplier)(line)?;
+    Ok((line, Item::Group(items, count)))
+}
 
+/// Parses a single stitch name, such as `k2tog` or `sl kwise`.
+fn stitch_type(line: &str) -> IResult<&str, Stitch> {
     // alt can't support all the stitches, so they are broken up by prefix when there are duplicates.
     // while a more verbose, this is probably faster anyways as it cuts down on duplicate checks.
-    let (line, stitch) = alt((
+    alt((
         preceded(
             tag("1"),
             alt((
@@ -155,14 +206,67 @@ fn padded_stitch(line: &str) -> IResult<&str, std::vec::Vec<Stitch>> {
         ),
         stitch("nostitch", Stitch::NoStitch),
         stitch("yo", Stitch::Yo),
-    ))(line)?;
+    ))(line)
+}
 
+fn stitch_item(line: &str) -> IResult<&str, Item> {
     let (line, _) = space0(line)?;
-    let (line, mult) = opt(multiplier)(line)?;
-    match mult {
-        Some(val) => Ok((line, vec![stitch; val])),
-        None => Ok((line, vec![stitch])),
+    let (line, stitch) = stitch_type(line)?;
+    let (line, color) = opt(color)(line)?;
+    let (line, _) = space0(line)?;
+    let (line, count) = opt(multiplier)(line)?;
+    Ok((line, Item::Stitch(stitch, color, count)))
+}
+
+/// Parses one comma separated part of a stitch line, either a stitch or a group in parenthesis.
+pub(crate) fn item(line: &str) -> IResult<&str, Item> {
+    alt((group_item, stitch_item))(line)
+}
+
+/// Adds the stitches of an item to `stitches` with its counts expanded, returns `false` if a count doesn't fit the sizes.
+fn expand(item: &Item, sizes: SizeSelection, stitches: &mut Vec<ColoredStitch>) -> bool {
+    let start = stitches.len();
+    let count = match item {
+        Item::Stitch(stitch, color, count) => {
+            stitches.push((*stitch, *color));
+            count
+        }
+        Item::Group(items, count) => {
+            for item in items {
+                if !expand(item, sizes, stitches) {
+                    return false;
+                }
+            }
+            count
+        }
+    };
+
+    let times = match count {
+        Some(count) => match count.pick(sizes) {
+            Some(times) => times,
+            None => return false,
+        },
+        None => 1,
+    };
+    let end = stitches.len();
+    for _ in 1..times {
+        stitches.extend_from_within(start..end);
     }
+    if times == 0 {
+        stitches.truncate(start);
+    }
+    true
+}
+
+fn padded_item(line: &str, sizes: SizeSelection) -> IResult<&str, std::vec::Vec<ColoredStitch>> {
+    let starting_line = line;
+    let (line, item) = item(line)?;
+
+    let mut stitches = std::vec::Vec::new();
+    if !expand(&item, sizes, &mut stitches) {
+        return Err(nom::Err::Error(Error::new(starting_line, ErrorKind::Count)));
+    }
+    Ok((line, stitches))
 }
 
 /// Parse a given line into stitches.
@@ -174,29 +278,45 @@ fn padded_stitch(line: &str) -> IResult<&str, std::vec::Vec<Stitch>> {
 /// * `line` - The line to parse
 /// * `line_number` - What line number this line is (used for error reporting)
 ///
+#[cfg(test)]
 pub fn parse_stitches(line: &str, line_number: usize) -> Result<VecDeque<Stitch>, ParseError> {
+    let (stitches, _) = parse_colored_stitches(line, line_number, SizeSelection::default())?;
+    Ok(stitches)
+}
+
+/// Parse a given line into stitches and the colors they are worked in.
+///
+/// The two returned `VecDeque`s are always the same length, stitches without a color are `None`.
+///
+/// # Arguments
+///
+/// * `line` - The line to parse
+/// * `line_number` - What line number this line is (used for error reporting)
+/// * `sizes` - Which size to pick from counts that give a number per size
+///
+pub fn parse_colored_stitches(line: &str, line_number: usize, sizes: SizeSelection) -> Result<(VecDeque<Stitch>, VecDeque<Option<char>>), ParseError> {
     let starting_line = line;
 
-    match separated_list1(char(','), alt((padded_group, padded_stitch)))(line) {
+    match separated_list1(char(','), |line| padded_item(line, sizes))(line) {
         Ok((line, stitches)) => {
-            // Combine all the above vectors into one.
-            let mut vec = VecDeque::new();
+            if !line.is_empty() {
+                return Err(into_parse_error(starting_line, line, line_number));
+            }
 
+            // Combine all the above vectors into one.
             let mut total_size = 0;
             for vec_to_append in &stitches {
                 total_size += vec_to_append.len();
             }
-            vec.reserve(total_size);
-
-            for vec_to_append in &stitches {
-                vec.extend(vec_to_append);
-            }
 
-            if !line.is_empty() {
-                return Err(into_parse_error(starting_line, line, line_number));
+            let mut stitch_vec = VecDeque::with_capacity(total_size);
+            let mut color_vec = VecDeque::with_capacity(total_size);
+            for (stitch, color) in stitches.into_iter().flatten() {
+                stitch_vec.push_back(stitch);
+                color_vec.push_back(color);
             }
 
-            Ok(vec)
+            Ok((stitch_vec, color_vec))
         }
 
         Err(_) => Err(into_parse_error(starting_line, line, line_number)),
@@ -216,6 +336,7 @@ mod test {
     }
 
     #[test]
+    #[allow(clippy::assertions_on_constants)]
     fn simple_exception() {
         if let Err(parse_error) = parse_stitches("k, p, bad", 2) {
             assert_eq!(parse_error.line_number(), 2);
@@ -269,4 +390,31 @@ mod test {
         assert_eq!(stitches.len(), 10);
         assert_eq!(stitches, VecDeque::from(vec![K, P, K, P, K, K, P, K, P, K]));
     }
+
+    #[test]
+    fn zero_multiplier() {
+        let stitches = parse_stitches("k, (p, (k, p) x2) x0, p x0, k", 0).unwrap();
+        assert_eq!(stitches, VecDeque::from(vec![K, K]));
+    }
+
+    #[test]
+    fn colors() {
+        let (stitches, colors) = parse_colored_stitches("k:A x2, (p:B, k) x2", 0, SizeSelection::default()).unwrap();
+        assert_eq!(stitches, VecDeque::from(vec![K, K, P, K, P, K]));
+        assert_eq!(colors, VecDeque::from(vec![Some('A'), Some('A'), Some('B'), None, Some('B'), None]));
+    }
+
+    #[test]
+    fn size_alternatives() {
+        let sizes = SizeSelection { index: 2, count: 3 };
+        let (stitches, _) = parse_colored_stitches("k x1 (2, 3), (p, k x2) x2 (3,4)", 0, sizes).unwrap();
+        assert_eq!(stitches, VecDeque::from(vec![K, K, K, P, K, K, P, K, K, P, K, K, P, K, K]));
+    }
+
+    #[test]
+    fn wrong_size_alternatives() {
+        let sizes = SizeSelection { index: 0, count: 3 };
+        assert!(parse_colored_stitches("k x1 (2)", 0, sizes).is_err());
+        assert!(parse_stitches("k x1 (2, 3)", 0).is_err());
+    }
 }
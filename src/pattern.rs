@@ -6,6 +6,7 @@ use std::{
     io::{BufRead, BufReader},
 };
 
+use crate::Dimensions;
 use crate::Options;
 use crate::ParseError;
 use crate::ParseErrorType;
@@ -13,14 +14,19 @@ use crate::Side;
 use crate::Stitch;
 
 use crate::parse_line;
+use crate::parse_line::SizeSelection;
 
 /// The representation of a knitting pattern.
 ///
 /// A pattern will be a rectangle in size.
 pub struct Pattern {
     lines: Vec<VecDeque<Stitch>>,
+    colors: Vec<VecDeque<Option<char>>>,
     pattern_width: usize,
+    padding: Vec<usize>,
     options: Options,
+    source: Option<Vec<(usize, String)>>,
+    size: usize,
 }
 
 fn calculate_line_width(stitches: &VecDeque<Stitch>) -> usize {
@@ -34,7 +40,8 @@ fn calculate_line_width(stitches: &VecDeque<Stitch>) -> usize {
 impl Pattern {
     /// Create a new Pattern from the given Reader.
     ///
-    /// This will pad the rows so they are all the same total width.
+    /// This will pad the rows so they are all the same total width.  If the pattern has sizes the
+    /// first size is used, see `for_size` to get the others.
     ///
     /// # Arguments
     ///
@@ -43,14 +50,11 @@ impl Pattern {
     pub fn new<R: std::io::Read>(reader: R) -> Result<Pattern, ParseError> {
         let reader = BufReader::new(reader);
 
-        let mut lines: Vec<VecDeque<Stitch>> = Vec::new();
-        let mut line_number = 1;
-        let mut pattern_width = 0;
-
-        // TODO: check first line is options, if so parse it out.
+        let mut source: Vec<(usize, String)> = Vec::new();
         let mut options = Options::default();
 
-        for line in reader.lines() {
+        // Options are read first so the sizes are known before any stitches are parsed.
+        for (line_number, line) in (1..).zip(reader.lines()) {
             match line {
                 Ok(line) => {
                     if line.starts_with("##") {
@@ -60,21 +64,88 @@ impl Pattern {
                         // Ignored line
                     } else {
                         // Treat everything else as a stitch
-                        let line_stitches = parse_line::parse_stitches(&line, line_number)?;
-                        let line_width = calculate_line_width(&line_stitches);
-                        pattern_width = cmp::max(pattern_width, line_width);
-
-                        lines.push(line_stitches);
+                        source.push((line_number, line));
                     }
                 }
                 Err(error) => {
                     return Err(ParseError::new(ParseErrorType::UnableToReadFromReader(Box::new(error)), line_number));
                 }
             }
-            line_number += 1;
         }
 
-        for (line_number, line) in lines.iter_mut().enumerate() {
+        Pattern::from_source(source, options, 0)
+    }
+
+    /// Create a new Pattern for the given size from the stitch lines it was read from.
+    fn from_source(source: Vec<(usize, String)>, options: Options, size: usize) -> Result<Pattern, ParseError> {
+        let sizes = SizeSelection {
+            index: size,
+            count: cmp::max(options.sizes.len(), 1),
+        };
+
+        let mut lines: Vec<VecDeque<Stitch>> = Vec::with_capacity(source.len());
+        let mut colors: Vec<VecDeque<Option<char>>> = Vec::with_capacity(source.len());
+        for (line_number, line) in &source {
+            let (line_stitches, line_colors) = parse_line::parse_colored_stitches(line, *line_number, sizes)?;
+
+            lines.push(line_stitches);
+            colors.push(line_colors);
+        }
+
+        let mut pattern = Pattern::from_parts(lines, colors, options)?;
+        pattern.source = Some(source);
+        pattern.size = size;
+        Ok(pattern)
+    }
+
+    /// Create the pattern for one of the sizes given in the `sizes` option.
+    ///
+    /// The pattern is re-read from the stitch lines it was created from, so this only works on a pattern
+    /// made by `new` or `for_size` that hasn't been changed since.  Any other pattern can only give its own
+    /// size, asking for another gives a `SizesNotKept` error.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - The name of the size, such as `M`
+    ///
+    pub fn for_size(&self, size: &str) -> Result<Pattern, ParseError> {
+        match (self.options.sizes.iter().position(|name| name == size), &self.source) {
+            (Some(index), Some(source)) => Pattern::from_source(source.clone(), self.options.clone(), index),
+            (Some(index), None) if index == self.size => self.with_rows(self.lines.clone(), self.colors.clone()),
+            (Some(_), None) => Err(ParseError::new(ParseErrorType::SizesNotKept, 0)),
+            (None, _) => Err(ParseError::new(ParseErrorType::UnknownSize(size.to_string()), 0)),
+        }
+    }
+
+    /// Create a new Pattern from rows worked out from this one, keeping its options and size.
+    ///
+    /// This will pad the rows so they are all the same total width.  `colors` must be the same shape as `lines`.
+    pub(crate) fn with_rows(&self, lines: Vec<VecDeque<Stitch>>, colors: Vec<VecDeque<Option<char>>>) -> Result<Pattern, ParseError> {
+        let mut pattern = Pattern::from_parts(lines, colors, self.options.clone())?;
+        pattern.size = self.size;
+        Ok(pattern)
+    }
+
+    /// Sets which of the `sizes` option's sizes the pattern is for.
+    #[cfg(feature = "serde")]
+    pub(crate) fn set_size(&mut self, size: &str) -> Result<(), ParseError> {
+        match self.options.sizes.iter().position(|name| name == size) {
+            Some(index) => {
+                self.size = index;
+                Ok(())
+            }
+            None => Err(ParseError::new(ParseErrorType::UnknownSize(size.to_string()), 0)),
+        }
+    }
+
+    /// Create a new Pattern from already parsed lines.
+    ///
+    /// This will pad the rows so they are all the same total width.  `colors` must be the same shape as `lines`.
+    pub(crate) fn from_parts(mut lines: Vec<VecDeque<Stitch>>, mut colors: Vec<VecDeque<Option<char>>>, options: Options) -> Result<Pattern, ParseError> {
+        let pattern_width = lines.iter().map(calculate_line_width).max().unwrap_or(0);
+        let mut padding = vec![0; lines.len()];
+
+        for (line_number, (line, line_colors)) in lines.iter_mut().zip(colors.iter_mut()).enumerate() {
             let line_width = calculate_line_width(line);
             if line_width == pattern_width {
                 continue;
@@ -82,21 +153,47 @@ impl Pattern {
 
             let mut needed_stitches = pattern_width - line_width;
 
-            if needed_stitches % 2 != 0 {
+            if !needed_stitches.is_multiple_of(2) {
                 let error_type = ParseErrorType::InvalidStitchCount(line_width);
 
                 return Err(ParseError::new(error_type, line_number));
             }
 
+            padding[line_number] = needed_stitches / 2;
             while needed_stitches != 0 {
                 line.push_front(Stitch::NoStitch);
                 line.push_back(Stitch::NoStitch);
+                line_colors.push_front(None);
+                line_colors.push_back(None);
 
                 needed_stitches -= 2;
             }
         }
 
-        Ok(Pattern { lines, pattern_width, options })
+        Ok(Pattern {
+            lines,
+            colors,
+            pattern_width,
+            padding,
+            options,
+            source: None,
+            size: 0,
+        })
+    }
+
+    /// Swap in new rows for the pattern, padding them again.
+    ///
+    /// The rows are given without their padding, see `unpadded_row`, so the pattern can get narrower.
+    /// On error the pattern is left as it was.  The rows no longer match the text the pattern was read from,
+    /// so `for_size` can't be used afterwards.
+    pub(crate) fn set_rows(&mut self, lines: Vec<VecDeque<Stitch>>, colors: Vec<VecDeque<Option<char>>>) -> Result<(), ParseError> {
+        let padded = Pattern::from_parts(lines, colors, self.options.clone())?;
+        self.lines = padded.lines;
+        self.colors = padded.colors;
+        self.pattern_width = padded.pattern_width;
+        self.padding = padded.padding;
+        self.source = None;
+        Ok(())
     }
 
     /// Returns the first line number for the pattern
@@ -109,11 +206,35 @@ impl Pattern {
         &self.lines
     }
 
+    /// Returns the colors for each stitch in `lines`, `None` when no color was given
+    pub fn colors(&self) -> &Vec<VecDeque<Option<char>>> {
+        &self.colors
+    }
+
+    /// Returns how many `nostitch` were added on each side of a row to line it up with the widest row
+    pub(crate) fn padding(&self, row: usize) -> usize {
+        self.padding[row]
+    }
+
+    /// Returns a row's stitches and colors without the `nostitch` padding added to line it up with the widest row
+    ///
+    /// Any `nostitch` written in the row itself is kept.
+    pub(crate) fn unpadded_row(&self, row: usize) -> (VecDeque<Stitch>, VecDeque<Option<char>>) {
+        let line = &self.lines[row];
+        let range = self.padding[row]..line.len() - self.padding[row];
+        (line.range(range.clone()).copied().collect(), self.colors[row].range(range).copied().collect())
+    }
+
     /// Return what side the pattern starts on
     pub fn starting_side(&self) -> Side {
         self.options.starting_side
     }
 
+    /// Returns the side the given row (index into `lines`) is worked on
+    pub fn row_side(&self, row: usize) -> Side {
+        self.options.row_side(row)
+    }
+
     /// Return if the patter is in the round
     pub fn in_round(&self) -> bool {
         self.options.in_round
@@ -123,6 +244,26 @@ impl Pattern {
     pub fn pattern_width(&self) -> usize {
         self.pattern_width
     }
+
+    /// Returns the name of the size this pattern is for, if the pattern has sizes
+    pub fn size(&self) -> Option<&str> {
+        self.options.sizes.get(self.size).map(String::as_str)
+    }
+
+    /// Returns all the options for the pattern
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+
+    /// Returns the finished width and height of the pattern, if a gauge was given
+    pub fn finished_dimensions(&self) -> Option<Dimensions> {
+        let gauge = self.options.gauge?;
+
+        Some(Dimensions {
+            width_cm: self.pattern_width as f64 * gauge.stitch_width_cm(),
+            height_cm: self.lines.len() as f64 * gauge.row_height_cm(),
+        })
+    }
 }
 
 #[cfg(test)]
@@ -150,6 +291,7 @@ mod test {
     }
 
     #[test]
+    #[allow(clippy::assertions_on_constants)]
     fn simple_exception() {
         let input = b"k x6\nk x7";
 
@@ -163,4 +305,69 @@ mod test {
             assert!(false, "Should not have been valid");
         }
     }
+
+    #[test]
+    fn padded_colors() {
+        let input = b"k:A x3\nk:B, k:A x3, k:B";
+        let pattern = Pattern::new(&input[..]).unwrap();
+
+        assert_eq!(
+            pattern.colors,
+            vec![
+                VecDeque::from(vec![None, Some('A'), Some('A'), Some('A'), None]),
+                VecDeque::from(vec![Some('B'), Some('A'), Some('A'), Some('A'), Some('B')]),
+            ]
+        );
+    }
+
+    #[test]
+    fn finished_dimensions() {
+        let input = b"## gauge=20x30/10cm\nk x40\np x40\nk x40";
+        let pattern = Pattern::new(&input[..]).unwrap();
+        let dimensions = pattern.finished_dimensions().unwrap();
+
+        assert!((dimensions.width_cm - 20.0).abs() < 1e-9);
+        assert!((dimensions.height_cm - 1.0).abs() < 1e-9);
+        assert!((dimensions.width_in() - 20.0 / 2.54).abs() < 1e-9);
+
+        let input = b"k x40";
+        assert!(Pattern::new(&input[..]).unwrap().finished_dimensions().is_none());
+    }
+
+    #[test]
+    fn sizes() {
+        let input = b"## sizes = S, M, L\nk x2 (4, 6), p x2";
+        let pattern = Pattern::new(&input[..]).unwrap();
+        assert_eq!(pattern.size(), Some("S"));
+        assert_eq!(pattern.pattern_width(), 4);
+
+        let large = pattern.for_size("L").unwrap();
+        assert_eq!(large.size(), Some("L"));
+        assert_eq!(large.lines(), &vec![VecDeque::from(vec![K, K, K, K, K, K, P, P])]);
+
+        if let Err(parse_error) = pattern.for_size("XL") {
+            if let ParseErrorType::UnknownSize(size) = parse_error.error_type() {
+                assert_eq!(size, "XL");
+            } else {
+                panic!("Wrong error type returned");
+            }
+        } else {
+            panic!("Should not have found the size");
+        }
+    }
+
+    #[test]
+    fn sizes_after_changes() {
+        let input = b"## sizes = S, M\nk x2 (4), p x2";
+        let pattern = Pattern::new(&input[..]).unwrap().for_size("M").unwrap();
+
+        let mirrored = pattern.mirror();
+        assert_eq!(mirrored.size(), Some("M"));
+        assert_eq!(mirrored.for_size("M").unwrap().lines(), mirrored.lines());
+        assert!(matches!(mirrored.for_size("S").err().unwrap().error_type(), ParseErrorType::SizesNotKept));
+
+        let mut edited = pattern.for_size("S").unwrap();
+        edited.replace_stitch(0, 0, P).unwrap();
+        assert!(matches!(edited.for_size("M").err().unwrap().error_type(), ParseErrorType::SizesNotKept));
+    }
 }
@@ -0,0 +1,340 @@
+//! Draws a pattern as a chart, as text, SVG or HTML.
+//!
+//! Charts are drawn the way they are read, with the first row at the bottom.  Right side row numbers are on
+//! the right of the chart and wrong side row numbers on the left.  Every chart is followed by its key, see
+//! `Pattern::legend`.
+
+use std::cmp;
+use std::collections::VecDeque;
+
+use crate::grid::to_cells;
+use crate::repeat::compress_row;
+
+use crate::LegendEntry;
+use crate::Pattern;
+use crate::Side;
+use crate::Stitch;
+
+/// How many pixels wide and tall one column of an SVG chart is.
+const CELL_SIZE: usize = 20;
+
+/// The colors used to fill stitches worked in `A`, `B` and so on.  Later letters wrap around.
+pub(crate) const YARN_COLORS: [&str; 8] = ["#e06666", "#6fa8dc", "#93c47d", "#ffd966", "#8e7cc3", "#f6b26b", "#76a5af", "#c27ba0"];
+
+/// Highlights every element of the page for a row when one of them is clicked, clicking it again clears it.
+const HIGHLIGHT_SCRIPT: &str = "<script>
+document.querySelectorAll('[data-row]').forEach(function (element) {
+  element.addEventListener('click', function () {
+    var row = element.getAttribute('data-row');
+    var selected = element.classList.contains('selected');
+    document.querySelectorAll('.selected').forEach(function (other) { other.classList.remove('selected'); });
+    if (!selected) {
+      document.querySelectorAll('[data-row=\"' + row + '\"]').forEach(function (other) { other.classList.add('selected'); });
+    }
+  });
+});
+</script>
+";
+
+/// Returns the longest symbol in the key, in characters.
+fn symbol_width(legend: &[LegendEntry]) -> usize {
+    legend.iter().map(|entry| entry.symbol.chars().count()).max().unwrap_or(0)
+}
+
+/// Returns the fill for a stitch on a chart.
+pub(crate) fn fill(stitch: Stitch, color: Option<char>) -> &'static str {
+    match (stitch, color) {
+        (Stitch::NoStitch, _) => "#cccccc",
+        (_, None) => "#ffffff",
+        (_, Some(color)) => YARN_COLORS[(color as usize).saturating_sub('A' as usize) % YARN_COLORS.len()],
+    }
+}
+
+/// Escapes text to go in XML or HTML.
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+impl Pattern {
+    /// Returns the text for a key entry, leaving out the wrong side for patterns worked in the round.
+    fn legend_text(&self, entry: &LegendEntry) -> String {
+        if self.options().in_round {
+            entry.instructions()
+        } else {
+            entry.to_string()
+        }
+    }
+
+    /// Draws the chart as text, using `Stitch::symbol` for each stitch.
+    pub fn render_text(&self) -> String {
+        let last_number = (self.lines().len() + self.first_line_number()).saturating_sub(1);
+        let number_width = last_number.to_string().len();
+
+        let mut out = String::new();
+        for (row, line) in self.lines().iter().enumerate().rev() {
+            let number = (row + self.first_line_number()).to_string();
+            let symbols: String = line.iter().map(Stitch::symbol).collect();
+            let text = if self.row_side(row) == Side::RS {
+                format!("{:number_width$} {} {}", "", symbols, number)
+            } else {
+                format!("{:>number_width$} {}", number, symbols)
+            };
+            out.push_str(text.trim_end());
+            out.push('\n');
+        }
+
+        let legend = self.legend();
+        let symbol_width = symbol_width(&legend);
+        if !legend.is_empty() {
+            out.push('\n');
+        }
+        for entry in &legend {
+            out.push_str(&format!("{:symbol_width$}  {}\n", entry.symbol, self.legend_text(entry)));
+        }
+        out
+    }
+
+    /// Draws the chart as an SVG image, filling each stitch with its color.
+    pub fn render_svg(&self) -> String {
+        let legend = self.legend();
+        let chart_height = self.lines().len() * CELL_SIZE;
+        // Key text is given half a cell per character, which fits the monospace font.
+        let text_x = 2 * CELL_SIZE + (symbol_width(&legend) + 1) * CELL_SIZE / 2;
+        let text_width = legend.iter().map(|entry| self.legend_text(entry).chars().count()).max().unwrap_or(0);
+        let width = cmp::max((self.pattern_width() + 4) * CELL_SIZE, text_x + (text_width + 2) * CELL_SIZE / 2);
+        let height = if legend.is_empty() {
+            chart_height
+        } else {
+            chart_height + (legend.len() + 1) * CELL_SIZE
+        };
+
+        let mut out = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\" font-family=\"monospace\" font-size=\"14\">\n",
+            width, height, width, height
+        );
+        for (row, (line, colors)) in self.lines().iter().zip(self.colors()).enumerate() {
+            let y = (self.lines().len() - 1 - row) * CELL_SIZE;
+            let text_y = y + CELL_SIZE * 3 / 4;
+
+            let mut column = 2;
+            for (stitch, color) in line.iter().zip(colors) {
+                let x = column * CELL_SIZE;
+                let stitch_width = stitch.width() * CELL_SIZE;
+                out.push_str(&format!(
+                    "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\" stroke=\"#888888\"/>\n",
+                    x,
+                    y,
+                    stitch_width,
+                    CELL_SIZE,
+                    fill(*stitch, *color)
+                ));
+                if *stitch != Stitch::NoStitch {
+                    out.push_str(&format!(
+                        "  <text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{}</text>\n",
+                        x + stitch_width / 2,
+                        text_y,
+                        escape(&stitch.symbol())
+                    ));
+                }
+                column += stitch.width();
+            }
+
+            let number_x = if self.row_side(row) == Side::RS { width - CELL_SIZE } else { CELL_SIZE };
+            out.push_str(&format!(
+                "  <text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{}</text>\n",
+                number_x,
+                text_y,
+                row + self.first_line_number()
+            ));
+        }
+
+        // The key goes under the chart, with the text lined up after the widest symbol.
+        for (index, entry) in legend.iter().enumerate() {
+            let text_y = chart_height + (index + 1) * CELL_SIZE + CELL_SIZE * 3 / 4;
+            out.push_str(&format!(
+                "  <text x=\"{}\" y=\"{}\" xml:space=\"preserve\">{}</text>\n",
+                2 * CELL_SIZE,
+                text_y,
+                escape(&entry.symbol)
+            ));
+            out.push_str(&format!(
+                "  <text x=\"{}\" y=\"{}\">{}</text>\n",
+                text_x,
+                text_y,
+                escape(&self.legend_text(entry))
+            ));
+        }
+        out.push_str("</svg>\n");
+        out
+    }
+
+    /// Writes a row the way it's worked, such as `p x2, k x2`.
+    ///
+    /// Right side rows are worked across the chart from right to left and wrong side rows from left to right using
+    /// the wrong side stitches, the same as `KnittingSession`.
+    fn worked_text(&self, row: usize) -> String {
+        let side = self.row_side(row);
+        let mut worked: Vec<(Stitch, Option<char>)> = self.lines()[row]
+            .iter()
+            .copied()
+            .zip(self.colors()[row].iter().copied())
+            .filter(|(stitch, _)| *stitch != Stitch::NoStitch)
+            .map(|(stitch, color)| (if side == Side::WS { stitch.wrong_side() } else { stitch }, color))
+            .collect();
+        if side == Side::RS || self.in_round() {
+            worked.reverse();
+        }
+
+        let (stitches, colors): (VecDeque<Stitch>, VecDeque<Option<char>>) = worked.into_iter().unzip();
+        compress_row(&to_cells(&stitches, &colors), 0).0
+    }
+
+    /// Writes a single HTML page with the chart, the instructions for each row as it is worked and a legend of the
+    /// stitches used.
+    ///
+    /// The styles and script are part of the page so it works offline.  Clicking a row of the chart or of the
+    /// instructions highlights that row in both.
+    pub fn render_html(&self) -> String {
+        let title = escape(self.options().title.as_deref().unwrap_or("Pattern"));
+        let row_name = if self.options().in_round { "Round" } else { "Row" };
+
+        let mut out = String::new();
+        out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+        out.push_str(&format!("<title>{}</title>\n", title));
+        out.push_str("<style>\n");
+        out.push_str("body { font-family: sans-serif; }\n");
+        out.push_str("table.chart { border-collapse: collapse; font-family: monospace; }\n");
+        out.push_str("table.chart td { border: 1px solid #888888; min-width: 1.5em; height: 1.5em; text-align: center; }\n");
+        out.push_str("table.chart td.number { border: none; }\n");
+        out.push_str("table.legend td { padding: 0 0.5em; }\n");
+        out.push_str("[data-row] { cursor: pointer; }\n");
+        out.push_str("tr.selected td, li.selected { outline: 2px solid #cc0000; }\n");
+        out.push_str("</style>\n</head>\n<body>\n");
+        out.push_str(&format!("<h1>{}</h1>\n", title));
+        if let Some(author) = &self.options().author {
+            out.push_str(&format!("<p>By {}</p>\n", escape(author)));
+        }
+        if let Some(notes) = &self.options().notes {
+            for note in notes.lines() {
+                out.push_str(&format!("<p>{}</p>\n", escape(note)));
+            }
+        }
+
+        out.push_str("<h2>Chart</h2>\n<table class=\"chart\">\n");
+        for (row, (line, colors)) in self.lines().iter().zip(self.colors()).enumerate().rev() {
+            let number = row + self.first_line_number();
+            let right_side = self.row_side(row) == Side::RS;
+
+            out.push_str(&format!("<tr data-row=\"{}\">", number));
+            out.push_str(&format!(
+                "<td class=\"number\">{}</td>",
+                if right_side { String::new() } else { number.to_string() }
+            ));
+            for (stitch, color) in line.iter().zip(colors) {
+                out.push_str(&format!(
+                    "<td colspan=\"{}\" title=\"{}\" style=\"background: {}\">{}</td>",
+                    stitch.width(),
+                    stitch,
+                    fill(*stitch, *color),
+                    escape(stitch.symbol().trim())
+                ));
+            }
+            out.push_str(&format!(
+                "<td class=\"number\">{}</td>",
+                if right_side { number.to_string() } else { String::new() }
+            ));
+            out.push_str("</tr>\n");
+        }
+        out.push_str("</table>\n");
+
+        out.push_str("<h2>Instructions</h2>\n<ol class=\"instructions\">\n");
+        for row in 0..self.lines().len() {
+            let number = row + self.first_line_number();
+            out.push_str(&format!(
+                "<li data-row=\"{}\" value=\"{}\">{} {} ({:?}): {}</li>\n",
+                number,
+                number,
+                row_name,
+                number,
+                self.row_side(row),
+                escape(&self.worked_text(row))
+            ));
+        }
+        out.push_str("</ol>\n");
+
+        out.push_str("<h2>Legend</h2>\n<table class=\"legend\">\n<tr><th>Symbol</th><th>Abbreviation</th><th>Right side</th>");
+        if !self.options().in_round {
+            out.push_str("<th>Wrong side</th>");
+        }
+        out.push_str("</tr>\n");
+        for entry in self.legend() {
+            out.push_str(&format!(
+                "<tr><td class=\"symbol\">{}</td><td>{}</td><td>{}</td>",
+                escape(entry.symbol.trim()),
+                escape(&entry.abbreviation),
+                escape(&entry.right_side)
+            ));
+            if !self.options().in_round {
+                out.push_str(&format!("<td>{}</td>", escape(&entry.wrong_side)));
+            }
+            out.push_str("</tr>\n");
+        }
+        out.push_str("</table>\n");
+
+        out.push_str(HIGHLIGHT_SCRIPT);
+        out.push_str("</body>\n</html>\n");
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn pattern(input: &str) -> Pattern {
+        Pattern::new(input.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn text() {
+        let chart = pattern("k, p, k2tog, yo\nssk, 1lcf, k\nk2tog, k2tog").render_text();
+        let (chart, key) = chart.split_once("\n\n").unwrap();
+        assert_eq!(chart, "   //  3\n2 \\<<|\n  |-/O 1");
+        assert_eq!(key.lines().count(), 6);
+        assert!(key.starts_with("|   k: knit on the right side, purl on the wrong side\n"));
+        assert!(key.contains("\nO   yo: yarn over\n"));
+        assert!(key.ends_with("\n<<  1lcf: slip 1 stitch to a cable needle and hold in front, knit 1, then knit 1 from the cable needle\n"));
+
+        let round = pattern("## in_round\nk, p").render_text();
+        assert!(round.ends_with("\n\n|  k: knit\n-  p: purl\n"));
+    }
+
+    #[test]
+    fn svg_and_html() {
+        let pattern = pattern("## title=Rib & cable\nk:A, p, 1rcb");
+
+        let svg = pattern.render_svg();
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("fill=\"#e06666\""));
+        assert!(svg.contains("&gt;&gt;"));
+        assert!(svg.contains(">1rcb: slip 1 stitch to a cable needle and hold in back"));
+
+        let html = pattern.render_html();
+        assert!(html.contains("<title>Rib &amp; cable</title>"));
+        assert!(html.contains("<td colspan=\"2\" title=\"1rcb\""));
+        assert!(html.contains("<script>"));
+        assert!(!html.contains("src="));
+    }
+
+    #[test]
+    fn html_instructions_and_legend() {
+        let html = pattern("## first_line=3\nk x2, p x2\nk2tog, p x2, k").render_html();
+        assert!(html.contains("<tr data-row=\"4\">"));
+        assert!(html.contains("<li data-row=\"3\" value=\"3\">Row 3 (RS): p x2, k x2</li>"));
+        assert!(html.contains("<li data-row=\"4\" value=\"4\">Row 4 (WS): p2tog, k x2, p</li>"));
+        assert!(html.contains("<td>k2tog</td><td>knit two stitches together</td><td>purl two stitches together</td>"));
+        assert!(!html.contains("<td>ssk</td>"));
+        let html = pattern("k x3\nk, p, k2tog").render_html();
+        assert!(html.contains("Row 2 (WS): p, k, p2tog</li>"));
+    }
+}
@@ -0,0 +1,444 @@
+//! Finds the repeats in a pattern.
+
+use crate::grid::{splits_stitch, Cell};
+use crate::Options;
+use crate::Pattern;
+
+/// A block of columns that repeats across every row of a pattern.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct HorizontalRepeat {
+    /// The first column of the first repeat.
+    pub start_column: usize,
+
+    /// How many columns one repeat covers.
+    pub width: usize,
+
+    /// How many times the repeat is worked.
+    pub count: usize,
+}
+
+/// A block of rows that repeats.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct VerticalRepeat {
+    /// The first row (index into `Pattern::lines`) of the first repeat.
+    pub start_row: usize,
+
+    /// How many rows one repeat covers.
+    pub height: usize,
+
+    /// How many times the rows are worked.
+    pub count: usize,
+}
+
+impl VerticalRepeat {
+    /// Returns the row just after the last repeat.
+    pub fn end_row(&self) -> usize {
+        self.start_row + self.height * self.count
+    }
+}
+
+/// The area of a chart to draw a repeat box around.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct RepeatBox {
+    /// The first row in the box.
+    pub start_row: usize,
+
+    /// How many rows are in the box.
+    pub rows: usize,
+
+    /// The first column in the box.
+    pub start_column: usize,
+
+    /// How many columns are in the box.
+    pub columns: usize,
+}
+
+/// A row written out with its repeat.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CompressedRow {
+    /// The row written the same way as a pattern line, such as `k, (p x2, k x2) x3, p`.
+    pub text: String,
+
+    /// The repeat used in `text`, if any.
+    pub repeat: Option<HorizontalRepeat>,
+}
+
+/// A pattern written out with its repeats.
+#[derive(Debug)]
+pub struct CompressedPattern {
+    rows: Vec<CompressedRow>,
+    vertical: Option<VerticalRepeat>,
+    repeat_box: Option<RepeatBox>,
+    first_line_number: usize,
+}
+
+impl CompressedPattern {
+    /// Returns every row written with its own repeat.
+    pub fn rows(&self) -> &Vec<CompressedRow> {
+        &self.rows
+    }
+
+    /// Returns the block of rows that repeats, if any.
+    pub fn vertical_repeat(&self) -> Option<VerticalRepeat> {
+        self.vertical
+    }
+
+    /// Returns the area to mark as the repeat on a chart, if any.
+    ///
+    /// This covers the columns repeated on every row and the rows that repeat, or all the rows if none do.
+    pub fn repeat_box(&self) -> Option<RepeatBox> {
+        self.repeat_box
+    }
+}
+
+impl std::fmt::Display for CompressedPattern {
+    /// Writes one line per row.  Rows in a vertical repeat are only written once, followed by how often to repeat them.
+    fn fmt(&self, out: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut row = 0;
+        while row < self.rows.len() {
+            writeln!(out, "Row {}: {}", row + self.first_line_number, self.rows[row].text)?;
+
+            if let Some(vertical) = self.vertical {
+                if row + 1 == vertical.start_row + vertical.height {
+                    writeln!(
+                        out,
+                        "Repeat rows {}-{} {} times.",
+                        vertical.start_row + self.first_line_number,
+                        row + self.first_line_number,
+                        vertical.count
+                    )?;
+                    row = vertical.end_row();
+                    continue;
+                }
+            }
+
+            row += 1;
+        }
+
+        Ok(())
+    }
+}
+
+impl HorizontalRepeat {
+    /// Returns the column just after the last repeat.
+    pub fn end_column(&self) -> usize {
+        self.start_column + self.width * self.count
+    }
+}
+
+/// Finds the repeat covering the most of `length` items as `(start, period, count)`.
+///
+/// `same(a, b)` says if two items match and `boundary(index)` says if a repeat may start or end
+/// just before the item at `index`.  A repeat has to be worked at least twice, when two repeats
+/// cover the same number of items the shorter one wins.
+fn find_repeat(length: usize, same: impl Fn(usize, usize) -> bool, boundary: impl Fn(usize) -> bool) -> Option<(usize, usize, usize)> {
+    let mut best: Option<(usize, usize, usize)> = None;
+
+    for period in 1..=length / 2 {
+        // matches[index] is set when the item repeats `period` items later.
+        let matches: Vec<bool> = (0..length - period).map(|index| same(index, index + period)).collect();
+
+        let mut run_start = 0;
+        while run_start < matches.len() {
+            if !matches[run_start] {
+                run_start += 1;
+                continue;
+            }
+
+            let mut run_end = run_start;
+            while run_end < matches.len() && matches[run_end] {
+                run_end += 1;
+            }
+
+            let count = (run_end - run_start + period) / period;
+            let covers = period * count;
+            if count >= 2
+                && boundary(run_start)
+                && boundary(run_start + covers)
+                && best.is_none_or(|(_, best_period, best_count)| covers > best_period * best_count)
+            {
+                best = Some((run_start, period, count));
+            }
+
+            run_start = run_end;
+        }
+    }
+
+    best
+}
+
+/// Finds the repeat covering the most columns in all the given rows.
+///
+/// A repeat has to be worked at least twice and can't cut a wide stitch in half.  When two repeats
+/// cover the same number of columns the narrower one wins.
+pub(crate) fn find_horizontal_repeat(rows: &[Vec<Cell>]) -> Option<HorizontalRepeat> {
+    let width = rows.iter().map(|row| row.len()).max()?;
+    let (start_column, width, count) = find_repeat(
+        width,
+        |a, b| rows.iter().all(|row| row.get(a) == row.get(b)),
+        |column| !rows.iter().any(|row| splits_stitch(row, column)),
+    )?;
+
+    Some(HorizontalRepeat { start_column, width, count })
+}
+
+/// Finds the block of rows that repeats the most.
+pub(crate) fn find_vertical_repeat(rows: &[Vec<Cell>]) -> Option<VerticalRepeat> {
+    let (start_row, height, count) = find_repeat(rows.len(), |a, b| rows[a] == rows[b], |_| true)?;
+
+    Some(VerticalRepeat { start_row, height, count })
+}
+
+/// Writes cells as comma separated stitches, runs of the same stitch use a multiplier like `k x3`.
+pub(crate) fn run_length_text(cells: &[Cell]) -> Vec<String> {
+    let mut runs: Vec<(Cell, usize)> = Vec::new();
+    for cell in cells.iter().filter(|cell| **cell != Cell::Continued) {
+        match runs.last_mut() {
+            Some((last, count)) if last == cell => *count += 1,
+            _ => runs.push((*cell, 1)),
+        }
+    }
+
+    runs.into_iter()
+        .filter_map(|(cell, count)| {
+            let (stitch, color) = match cell {
+                Cell::Stitch(stitch, color) => (stitch, color),
+                Cell::Continued => return None,
+            };
+            let mut text = stitch.to_string();
+            if let Some(color) = color {
+                text.push(':');
+                text.push(color);
+            }
+            if count > 1 {
+                text.push_str(&format!(" x{}", count));
+            }
+            Some(text)
+        })
+        .collect()
+}
+
+/// Writes a row using a repeat where it helps, such as `k, (p x2, k x2) x3, p`.
+///
+/// Returns the text and the repeat that was used.
+///
+/// # Arguments
+///
+/// * `cells` - The row, one cell per column
+/// * `padding` - How many `nostitch` columns were added on each side to line up the pattern, see `Pattern::padding`
+///
+pub(crate) fn compress_row(cells: &[Cell], padding: usize) -> (String, Option<HorizontalRepeat>) {
+    // Drop the padding added to line up the pattern, it's added back when the text is read.  Any `nostitch`
+    // written in the row is kept.
+    let start = padding;
+    let cells = &cells[padding..cells.len() - padding];
+
+    let mut best: Option<(usize, String, HorizontalRepeat)> = None;
+    if let Some(found) = find_horizontal_repeat(&[cells.to_vec()]) {
+        // The same repeat can often start a few columns later, use whichever has the simplest repeat.
+        for start_column in found.start_column..found.start_column + found.width {
+            let repeat = HorizontalRepeat { start_column, ..found };
+            let fits = repeat.end_column() <= cells.len()
+                && !splits_stitch(cells, start_column)
+                && !splits_stitch(cells, repeat.end_column())
+                && (start_column..repeat.end_column() - repeat.width).all(|column| cells[column] == cells[column + repeat.width]);
+            if !fits {
+                continue;
+            }
+
+            let unit = run_length_text(&cells[start_column..start_column + repeat.width]);
+            if unit.len() > 1 {
+                let mut parts = run_length_text(&cells[..start_column]);
+                parts.push(format!("({}) x{}", unit.join(", "), repeat.count));
+                parts.extend(run_length_text(&cells[repeat.end_column()..]));
+
+                let text = parts.join(", ");
+                if best
+                    .as_ref()
+                    .is_none_or(|(best_unit, best_text, _)| (unit.len(), text.len()) < (*best_unit, best_text.len()))
+                {
+                    best = Some((unit.len(), text, repeat));
+                }
+            }
+        }
+    }
+
+    match best {
+        Some((_, text, mut repeat)) => {
+            repeat.start_column += start;
+            (text, Some(repeat))
+        }
+        None => (run_length_text(cells).join(", "), None),
+    }
+}
+
+impl Pattern {
+    /// Finds the widest block of columns that repeats across every row, if any.
+    pub fn horizontal_repeat(&self) -> Option<HorizontalRepeat> {
+        find_horizontal_repeat(&self.cells())
+    }
+
+    /// Finds the block of rows repeated the most, if any.
+    pub fn vertical_repeat(&self) -> Option<VerticalRepeat> {
+        find_vertical_repeat(&self.cells())
+    }
+
+    /// Writes the pattern out using the shortest repeats that can be found.
+    ///
+    /// This is the reverse of expanding groups when a line is read, each row's text reads back as the same stitches.
+    pub fn compress(&self) -> CompressedPattern {
+        let cells = self.cells();
+        let rows = cells
+            .iter()
+            .enumerate()
+            .map(|(index, row)| {
+                let (text, repeat) = compress_row(row, self.padding(index));
+                CompressedRow { text, repeat }
+            })
+            .collect();
+
+        let vertical = find_vertical_repeat(&cells);
+        let repeat_box = match (find_horizontal_repeat(&cells), vertical) {
+            (None, None) => None,
+            (horizontal, vertical) => Some(RepeatBox {
+                start_row: vertical.map_or(0, |vertical| vertical.start_row),
+                rows: vertical.map_or(cells.len(), |vertical| vertical.height),
+                start_column: horizontal.map_or(0, |horizontal| horizontal.start_column),
+                columns: horizontal.map_or(self.pattern_width(), |horizontal| horizontal.width),
+            }),
+        };
+
+        CompressedPattern {
+            rows,
+            vertical,
+            repeat_box,
+            first_line_number: self.first_line_number(),
+        }
+    }
+
+    /// Writes the pattern as text that `Pattern::new` reads back as the same pattern.
+    ///
+    /// The options come first, then each row written the same way as `compress`.  Only this pattern's size
+    /// is written, so the `sizes` option is left out.
+    pub fn to_text(&self) -> String {
+        let options = Options {
+            sizes: Vec::new(),
+            ..self.options().clone()
+        };
+
+        let mut text = String::new();
+        for line in options.to_lines() {
+            text.push_str(&line);
+            text.push('\n');
+        }
+        for row in self.compress().rows() {
+            text.push_str(&row.text);
+            text.push('\n');
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ribbing() {
+        let input = b"k, (p x2, k x2) x3, p\nk, (k x2, p x2) x3, p";
+        let repeat = Pattern::new(&input[..]).unwrap().horizontal_repeat().unwrap();
+
+        assert_eq!(
+            repeat,
+            HorizontalRepeat {
+                start_column: 1,
+                width: 4,
+                count: 3,
+            }
+        );
+        assert_eq!(repeat.end_column(), 13);
+    }
+
+    #[test]
+    fn cables() {
+        let input = b"(p, 1lcf) x3\n(p, k x2) x3";
+        let repeat = Pattern::new(&input[..]).unwrap().horizontal_repeat().unwrap();
+
+        assert_eq!(repeat.width, 3);
+        assert_eq!(repeat.count, 3);
+    }
+
+    #[test]
+    fn no_repeat() {
+        let input = b"k, p, k2tog, ssk";
+        assert!(Pattern::new(&input[..]).unwrap().horizontal_repeat().is_none());
+    }
+
+    #[test]
+    fn compress_rows() {
+        let input = "nostitch, k, (p x2, k x2) x3, p, nostitch\nk:A x4, p x2, 2lcf, p x2, 2lcf, p x2, k:A x4";
+        let pattern = Pattern::new(input.as_bytes()).unwrap();
+        let compressed = pattern.compress();
+
+        assert_eq!(compressed.rows()[0].text, "nostitch, k, (p x2, k x2) x3, p, nostitch");
+        assert_eq!(compressed.rows()[0].repeat.unwrap().start_column, 5);
+        assert_eq!(compressed.rows()[1].text, "k:A x4, (p x2, 2lcf) x2, p x2, k:A x4");
+
+        for (row, compressed_row) in compressed.rows().iter().enumerate() {
+            let reread = Pattern::new(compressed_row.text.as_bytes()).unwrap();
+            assert_eq!(reread.lines()[0], pattern.unpadded_row(row).0);
+        }
+    }
+
+    #[test]
+    fn compress_vertical() {
+        let input = b"k x4\n(k, p) x2\n(p, k) x2\n(k, p) x2\n(p, k) x2\n(k, p) x2\n(p, k) x2\np x4";
+        let compressed = Pattern::new(&input[..]).unwrap().compress();
+
+        assert_eq!(
+            compressed.vertical_repeat(),
+            Some(VerticalRepeat {
+                start_row: 1,
+                height: 2,
+                count: 3,
+            })
+        );
+        assert_eq!(
+            compressed.to_string(),
+            "Row 1: k x4\nRow 2: (k, p) x2\nRow 3: (p, k) x2\nRepeat rows 2-3 3 times.\nRow 8: p x4\n"
+        );
+        assert_eq!(
+            compressed.repeat_box(),
+            Some(RepeatBox {
+                start_row: 1,
+                rows: 2,
+                start_column: 0,
+                columns: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn written_nostitch_reads_back() {
+        let input = b"nostitch, k x2, nostitch\nk x6\nk x2";
+        let pattern = Pattern::new(&input[..]).unwrap();
+        let text = pattern.to_text();
+        assert_eq!(text, "nostitch, k x2, nostitch\nk x6\nk x2\n");
+        assert_eq!(Pattern::new(text.as_bytes()).unwrap().lines(), pattern.lines());
+    }
+
+    #[test]
+    fn text_reads_back() {
+        let input = b"## start_wrong_side\n## gauge=20x28/4in\n## notes=one\n## notes=two\nk x2, (p:A, 1lcf) x3, k x2\nk, ssk x3, k";
+        let pattern = Pattern::new(&input[..]).unwrap();
+        let text = pattern.to_text();
+        assert_eq!(
+            text,
+            "## start_wrong_side\n## gauge=20x28/4in\n## notes=one\n## notes=two\nk x2, (p:A, 1lcf) x3, k x2\nk, ssk x3, k\n"
+        );
+
+        let read = Pattern::new(text.as_bytes()).unwrap();
+        assert_eq!(read.lines(), pattern.lines());
+        assert_eq!(read.colors(), pattern.colors());
+    }
+}
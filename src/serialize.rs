@@ -0,0 +1,194 @@
+//! Serde support, turned on with the `serde` feature.
+//!
+//! A pattern is written as:
+//!
+//! ```json
+//! {
+//!   "options": {
+//!     "first_line_number": 1,
+//!     "starting_side": "RS",
+//!     "in_round": false,
+//!     "gauge": { "stitches": 22.0, "rows": 30.0, "length": "10cm" },
+//!     "needle_size": 4.5,
+//!     "yarn_weight": "worsted",
+//!     "title": null,
+//!     "author": null,
+//!     "notes": null,
+//!     "sizes": ["S", "M"],
+//!     "lints": { "allowed": ["long_run"], "max_run": 20 }
+//!   },
+//!   "size": "S",
+//!   "width": 4,
+//!   "rows": [
+//!     { "stitches": ["k", "p", "k", "p"], "colors": [null, null, "A", "A"] },
+//!     { "stitches": ["nostitch", "k2tog", "ssk", "nostitch"], "colors": [null, null, null, null] }
+//!   ]
+//! }
+//! ```
+//!
+//! Each stitch is written the same way as in a pattern line, such as `k2tog`, `sl kwise` or `2lcf`.  Rows
+//! include their `nostitch` padding and `width` is the width of the padded rows.  `size` is which of the
+//! `sizes` the rows are for, it's `null` when the pattern has no sizes.  Only that size's rows are written,
+//! so `Pattern::for_size` can't be used on a pattern read back.  The lints in `allowed` are written by name,
+//! the same as in an `## allow=` line.
+//!
+//! When reading, any missing option uses its default, `width` is worked out again, `colors` can be left
+//! out for rows in the main color and the rows are padded the same way as `Pattern::new`.
+
+use std::collections::VecDeque;
+
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::Options;
+use crate::Pattern;
+use crate::Stitch;
+
+impl Serialize for Stitch {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Stitch {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Stitch, D::Error> {
+        let abbreviation = String::deserialize(deserializer)?;
+        abbreviation.parse().map_err(|_| D::Error::custom(format!("unknown stitch `{}`", abbreviation)))
+    }
+}
+
+#[derive(Serialize)]
+struct RowRef<'a> {
+    stitches: &'a VecDeque<Stitch>,
+    colors: &'a VecDeque<Option<char>>,
+}
+
+#[derive(Serialize)]
+struct PatternRef<'a> {
+    options: &'a Options,
+    size: Option<&'a str>,
+    width: usize,
+    rows: Vec<RowRef<'a>>,
+}
+
+#[derive(Deserialize)]
+struct RowData {
+    stitches: VecDeque<Stitch>,
+    #[serde(default)]
+    colors: Option<VecDeque<Option<char>>>,
+}
+
+#[derive(Deserialize)]
+struct PatternData {
+    #[serde(default)]
+    options: Options,
+    #[serde(default)]
+    size: Option<String>,
+    rows: Vec<RowData>,
+}
+
+impl Serialize for Pattern {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let rows = self
+            .lines()
+            .iter()
+            .zip(self.colors())
+            .map(|(stitches, colors)| RowRef { stitches, colors })
+            .collect();
+
+        PatternRef {
+            options: self.options(),
+            size: self.size(),
+            width: self.pattern_width(),
+            rows,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Pattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Pattern, D::Error> {
+        let data = PatternData::deserialize(deserializer)?;
+
+        let mut lines = Vec::with_capacity(data.rows.len());
+        let mut colors = Vec::with_capacity(data.rows.len());
+        for (row, row_data) in data.rows.into_iter().enumerate() {
+            let row_colors = row_data.colors.unwrap_or_else(|| VecDeque::from(vec![None; row_data.stitches.len()]));
+            if row_colors.len() != row_data.stitches.len() {
+                return Err(D::Error::custom(format!(
+                    "row {} has {} colors for {} stitches",
+                    row,
+                    row_colors.len(),
+                    row_data.stitches.len()
+                )));
+            }
+
+            lines.push(row_data.stitches);
+            colors.push(row_colors);
+        }
+
+        let mut pattern = Pattern::from_parts(lines, colors, data.options).map_err(D::Error::custom)?;
+        if let Some(size) = data.size {
+            pattern.set_size(&size).map_err(D::Error::custom)?;
+        }
+        Ok(pattern)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Stitch::*;
+
+    #[test]
+    fn round_trip() {
+        let input = b"## in_round\n## gauge=22x30/10cm\nk, p:A x2, k\nk2tog, 1lcf, k";
+        let pattern = Pattern::new(&input[..]).unwrap();
+
+        let json = serde_json::to_string(&pattern).unwrap();
+        let read: Pattern = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(read.lines(), pattern.lines());
+        assert_eq!(read.colors(), pattern.colors());
+        assert!(read.in_round());
+        assert_eq!(read.options().gauge, pattern.options().gauge);
+    }
+
+    #[test]
+    fn schema() {
+        let pattern = Pattern::new(&b"## first_line=3\nsl kwise:B, 2rcb, k"[..]).unwrap();
+        let json = serde_json::to_value(&pattern).unwrap();
+
+        assert_eq!(json["width"], 6);
+        assert_eq!(json["size"], serde_json::Value::Null);
+        assert_eq!(json["options"]["first_line_number"], 3);
+        assert_eq!(json["options"]["starting_side"], "RS");
+        assert_eq!(json["rows"][0]["stitches"], serde_json::json!(["sl kwise", "2rcb", "k"]));
+        assert_eq!(json["rows"][0]["colors"], serde_json::json!(["B", null, null]));
+    }
+
+    #[test]
+    fn size() {
+        let pattern = Pattern::new(&b"## sizes=S, M\nk x2 (4)"[..]).unwrap().for_size("M").unwrap();
+        let json = serde_json::to_string(&pattern).unwrap();
+        assert!(json.contains(r#""size":"M""#));
+
+        let read: Pattern = serde_json::from_str(&json).unwrap();
+        assert_eq!(read.size(), Some("M"));
+        assert_eq!(read.pattern_width(), 4);
+        assert!(read.for_size("S").is_err());
+
+        assert!(serde_json::from_str::<Pattern>(r#"{ "options": { "sizes": ["S"] }, "size": "XL", "rows": [] }"#).is_err());
+    }
+
+    #[test]
+    fn read_minimal() {
+        let pattern: Pattern = serde_json::from_str(r#"{ "rows": [{ "stitches": ["k", "k", "k"] }, { "stitches": ["ssk"] }] }"#).unwrap();
+        assert_eq!(pattern.pattern_width(), 3);
+        assert_eq!(pattern.lines()[1], VecDeque::from(vec![NoStitch, Ssk, NoStitch]));
+        assert_eq!(pattern.starting_side(), crate::Side::RS);
+
+        assert!(serde_json::from_str::<Pattern>(r#"{ "rows": [{ "stitches": ["k", "k"] }, { "stitches": ["p"] }] }"#).is_err());
+        assert!(serde_json::from_str::<Pattern>(r#"{ "rows": [{ "stitches": ["knit"] }] }"#).is_err());
+    }
+}
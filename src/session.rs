@@ -0,0 +1,353 @@
+//! Keeps track of where a knitter is while working a pattern.
+
+use std::str::FromStr;
+
+use crate::ParseError;
+use crate::ParseErrorType;
+use crate::Pattern;
+use crate::Side;
+use crate::Stitch;
+
+/// A stitch as the knitter works it.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct WorkedStitch {
+    /// The stitch to work, on a wrong side row this is the wrong side version of the chart stitch.
+    pub stitch: Stitch,
+
+    /// The stitch as shown on the chart.
+    pub chart_stitch: Stitch,
+
+    /// The index of the stitch within `Pattern::lines()[row]`.
+    pub index: usize,
+
+    /// The color to work it in, `None` for the main color.
+    pub color: Option<char>,
+}
+
+/// Where a session is, so it can be saved and picked back up.
+///
+/// It's written as `row=R stitch=S`, both counted from 0.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SessionState {
+    /// The row (index into `Pattern::lines`) being worked.
+    pub row: usize,
+
+    /// How many stitches of the row have been worked.
+    pub stitch: usize,
+}
+
+impl std::fmt::Display for SessionState {
+    fn fmt(&self, out: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(out, "row={} stitch={}", self.row, self.stitch)
+    }
+}
+
+impl FromStr for SessionState {
+    type Err = ParseError;
+
+    fn from_str(state: &str) -> Result<SessionState, ParseError> {
+        let invalid = || ParseError::new(ParseErrorType::InvalidSyntaxRange(0, state.len().saturating_sub(1)), 0);
+
+        let mut row = None;
+        let mut stitch = None;
+        for part in state.split_whitespace() {
+            match part.split_once('=') {
+                Some(("row", value)) => row = Some(value.parse::<usize>().map_err(|_| invalid())?),
+                Some(("stitch", value)) => stitch = Some(value.parse::<usize>().map_err(|_| invalid())?),
+                _ => return Err(invalid()),
+            }
+        }
+
+        match (row, stitch) {
+            (Some(row), Some(stitch)) => Ok(SessionState { row, stitch }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Walks through a pattern one stitch at a time, in the order they are worked.
+///
+/// Right side rows are read from the chart right to left and wrong side rows left to right.  Patterns in
+/// the round are always read right to left.  Any `nostitch` padding is skipped.
+pub struct KnittingSession<'a> {
+    pattern: &'a Pattern,
+    row: usize,
+    stitch: usize,
+    order: Vec<usize>,
+}
+
+impl<'a> KnittingSession<'a> {
+    /// Starts a session at the first stitch of the pattern.
+    pub fn new(pattern: &'a Pattern) -> KnittingSession<'a> {
+        let mut session = KnittingSession {
+            pattern,
+            row: 0,
+            stitch: 0,
+            order: Vec::new(),
+        };
+        session.order = session.working_order(0);
+        session
+    }
+
+    /// Picks a session back up from a saved state.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - The pattern being worked
+    /// * `state` - Where the session was
+    ///
+    pub fn restore(pattern: &'a Pattern, state: SessionState) -> Result<KnittingSession<'a>, ParseError> {
+        let mut session = KnittingSession::new(pattern);
+        session.jump_to_row(state.row)?;
+        if state.stitch > session.order.len() {
+            return Err(ParseError::new(ParseErrorType::ColumnOutOfRange(state.stitch), state.row));
+        }
+        session.stitch = state.stitch;
+        Ok(session)
+    }
+
+    /// Returns where the session is, so it can be saved.
+    pub fn state(&self) -> SessionState {
+        SessionState {
+            row: self.row,
+            stitch: self.stitch,
+        }
+    }
+
+    /// Returns the indexes of the row's stitches in the order they are worked.
+    fn working_order(&self, row: usize) -> Vec<usize> {
+        let line = match self.pattern.lines().get(row) {
+            Some(line) => line,
+            None => return Vec::new(),
+        };
+
+        let mut order: Vec<usize> = (0..line.len()).filter(|index| line[*index] != Stitch::NoStitch).collect();
+        if self.pattern.row_side(row) == Side::RS || self.pattern.in_round() {
+            order.reverse();
+        }
+        order
+    }
+
+    /// Returns the row (index into `Pattern::lines`) being worked.
+    pub fn row(&self) -> usize {
+        self.row
+    }
+
+    /// Returns the row number being worked, counted from the pattern's first line number.
+    pub fn row_number(&self) -> usize {
+        self.row + self.pattern.first_line_number()
+    }
+
+    /// Returns how many stitches of the current row have been worked.
+    pub fn stitches_worked(&self) -> usize {
+        self.stitch
+    }
+
+    /// Returns how many stitches the current row has.
+    pub fn stitches_in_row(&self) -> usize {
+        self.order.len()
+    }
+
+    /// Returns the side of the current row.
+    pub fn side(&self) -> Side {
+        self.pattern.row_side(self.row)
+    }
+
+    /// Returns if every stitch in the pattern has been worked.
+    pub fn is_finished(&self) -> bool {
+        self.row >= self.pattern.lines().len()
+    }
+
+    /// Returns the next stitch to work, if the pattern isn't finished.
+    pub fn current(&self) -> Option<WorkedStitch> {
+        let index = *self.order.get(self.stitch)?;
+        let chart_stitch = self.pattern.lines()[self.row][index];
+        let stitch = if self.side() == Side::WS { chart_stitch.wrong_side() } else { chart_stitch };
+
+        Some(WorkedStitch {
+            stitch,
+            chart_stitch,
+            index,
+            color: self.pattern.colors()[self.row][index],
+        })
+    }
+
+    /// Marks the current stitch as worked, moving on to the next row at the end of a row.
+    ///
+    /// Returns the stitch that is now current, if the pattern isn't finished.
+    pub fn next_stitch(&mut self) -> Option<WorkedStitch> {
+        if self.is_finished() {
+            return None;
+        }
+
+        self.stitch += 1;
+        if self.stitch >= self.order.len() {
+            self.row += 1;
+            self.stitch = 0;
+            self.order = self.working_order(self.row);
+        }
+        self.current()
+    }
+
+    /// Steps back one stitch, moving to the end of the row before at the start of a row.
+    ///
+    /// Returns the stitch that is now current, or `None` if already at the start of the pattern.
+    pub fn previous_stitch(&mut self) -> Option<WorkedStitch> {
+        if self.stitch > 0 {
+            self.stitch -= 1;
+        } else if self.row > 0 {
+            self.row -= 1;
+            self.order = self.working_order(self.row);
+            self.stitch = self.order.len().saturating_sub(1);
+        } else {
+            return None;
+        }
+        self.current()
+    }
+
+    /// Moves to the start of the given row (index into `Pattern::lines`).
+    ///
+    /// # Arguments
+    ///
+    /// * `row` - The row to move to
+    ///
+    pub fn jump_to_row(&mut self, row: usize) -> Result<(), ParseError> {
+        if row > self.pattern.lines().len() {
+            return Err(ParseError::new(ParseErrorType::RowOutOfRange(row), row));
+        }
+
+        self.row = row;
+        self.stitch = 0;
+        self.order = self.working_order(row);
+        Ok(())
+    }
+
+    /// Returns how many times the pattern's repeated rows have been fully worked.
+    pub fn repeats_completed(&self) -> usize {
+        match self.pattern.vertical_repeat() {
+            Some(repeat) if self.row > repeat.start_row => ((self.row - repeat.start_row) / repeat.height).min(repeat.count),
+            _ => 0,
+        }
+    }
+
+    /// Returns how many times the repeat across the current row has been fully worked.
+    pub fn row_repeats_completed(&self) -> usize {
+        let repeat = match self.pattern.horizontal_repeat() {
+            Some(repeat) => repeat,
+            None => return 0,
+        };
+
+        // Work out which columns have been worked so far.
+        let line = match self.pattern.lines().get(self.row) {
+            Some(line) => line,
+            None => return 0,
+        };
+        let mut start_columns = Vec::with_capacity(line.len());
+        let mut column = 0;
+        for stitch in line {
+            start_columns.push(column);
+            column += stitch.width();
+        }
+
+        (0..repeat.count)
+            .filter(|repeat_index| {
+                let start = repeat.start_column + repeat_index * repeat.width;
+                let end = start + repeat.width;
+                self.order[..self.stitch]
+                    .iter()
+                    .filter(|index| start_columns[**index] >= start && start_columns[**index] < end)
+                    .map(|index| line[*index].width())
+                    .sum::<usize>()
+                    == repeat.width
+            })
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Stitch::*;
+
+    fn pattern(input: &str) -> Pattern {
+        Pattern::new(input.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn reading_direction() {
+        let pattern = pattern("k, p, k2tog\nk, p, k2tog");
+        let mut session = KnittingSession::new(&pattern);
+
+        // Right side, read right to left
+        assert_eq!(session.current().unwrap().stitch, K2Tog);
+        assert_eq!(session.next_stitch().unwrap().stitch, P);
+        assert_eq!(session.next_stitch().unwrap().stitch, K);
+
+        // Wrong side, read left to right and worked as the wrong side stitch
+        let worked = session.next_stitch().unwrap();
+        assert_eq!(session.side(), Side::WS);
+        assert_eq!(worked.stitch, P);
+        assert_eq!(worked.chart_stitch, K);
+        assert_eq!(session.next_stitch().unwrap().stitch, K);
+        assert_eq!(session.next_stitch().unwrap().stitch, P2Tog);
+
+        assert!(session.next_stitch().is_none());
+        assert!(session.is_finished());
+    }
+
+    #[test]
+    fn in_the_round() {
+        let pattern = pattern("## in_round\nk, p\nk, p");
+        let mut session = KnittingSession::new(&pattern);
+        session.jump_to_row(1).unwrap();
+
+        assert_eq!(session.side(), Side::RS);
+        assert_eq!(session.current().unwrap().stitch, P);
+    }
+
+    #[test]
+    fn previous_and_padding() {
+        let pattern = pattern("k x2\np x4");
+        let mut session = KnittingSession::new(&pattern);
+        assert_eq!(session.stitches_in_row(), 2);
+
+        session.next_stitch();
+        session.next_stitch();
+        assert_eq!(session.row(), 1);
+        assert_eq!(session.previous_stitch().unwrap().index, 1);
+        assert_eq!(session.row(), 0);
+        session.previous_stitch();
+        assert!(session.previous_stitch().is_none());
+    }
+
+    #[test]
+    fn repeats() {
+        let pattern = pattern("(k, p) x3\n(p, k) x3\n(k, p) x3\n(p, k) x3");
+        let mut session = KnittingSession::new(&pattern);
+        for _ in 0..4 {
+            session.next_stitch();
+        }
+        assert_eq!(session.row_repeats_completed(), 2);
+
+        session.jump_to_row(2).unwrap();
+        assert_eq!(session.repeats_completed(), 1);
+    }
+
+    #[test]
+    fn save_and_restore() {
+        let pattern = pattern("k x4\nk x4");
+        let mut session = KnittingSession::new(&pattern);
+        for _ in 0..6 {
+            session.next_stitch();
+        }
+
+        let saved = session.state().to_string();
+        assert_eq!(saved, "row=1 stitch=2");
+
+        let restored = KnittingSession::restore(&pattern, saved.parse().unwrap()).unwrap();
+        assert_eq!(restored.state(), session.state());
+        assert!("row=1".parse::<SessionState>().is_err());
+        assert!(KnittingSession::restore(&pattern, SessionState { row: 1, stitch: 5 }).is_err());
+    }
+}
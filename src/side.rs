@@ -1,5 +1,6 @@
 /// Represents the side of the line.
 #[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Side {
     RS,
     WS,
@@ -0,0 +1,250 @@
+//! Reads and writes charts drawn in a spreadsheet, saved as CSV or TSV.
+//!
+//! Each cell holds one stitch written the same way as in a pattern line, with an optional color such as
+//! `p:A`.  The bottom line of the file is the first row of the pattern.  A wide stitch like `2lcf` covers
+//! `width()` cells, the cells after the first can be left empty or hold the same stitch.  Any other empty
+//! cell is a `nostitch`.
+
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read};
+
+use crate::Options;
+use crate::ParseError;
+use crate::ParseErrorType;
+use crate::Pattern;
+use crate::Stitch;
+
+/// A cell of the file, giving its text and where it starts and ends in the line.
+struct SheetCell {
+    text: String,
+    start: usize,
+    end: usize,
+}
+
+/// Splits a line into cells, allowing cells to be quoted with `"` the way spreadsheets save them.
+fn split_cells(line: &str, delimiter: char) -> Vec<SheetCell> {
+    let mut cells = Vec::new();
+    let mut text = String::new();
+    let mut start = 0;
+    let mut quoted = false;
+    let mut chars = line.chars().enumerate().peekable();
+
+    while let Some((position, c)) = chars.next() {
+        if quoted {
+            if c == '"' {
+                if chars.peek().map(|(_, next)| *next) == Some('"') {
+                    text.push('"');
+                    chars.next();
+                } else {
+                    quoted = false;
+                }
+            } else {
+                text.push(c);
+            }
+        } else if c == '"' {
+            quoted = true;
+        } else if c == delimiter {
+            cells.push(SheetCell {
+                text: text.trim().to_string(),
+                start,
+                end: position.saturating_sub(1).max(start),
+            });
+            text.clear();
+            start = position + 1;
+        } else {
+            text.push(c);
+        }
+    }
+
+    let length = line.chars().count();
+    cells.push(SheetCell {
+        text: text.trim().to_string(),
+        start,
+        end: length.saturating_sub(1).max(start),
+    });
+    cells
+}
+
+/// Reads a cell's stitch and color, such as `k2tog` or `p:A`.
+fn read_cell(cell: &SheetCell, line_number: usize) -> Result<(Stitch, Option<char>), ParseError> {
+    let invalid = || ParseError::new(ParseErrorType::InvalidSyntaxRange(cell.start, cell.end), line_number);
+
+    let (abbreviation, color) = match cell.text.rsplit_once(':') {
+        Some((abbreviation, color)) => {
+            let mut chars = color.trim().chars();
+            match (chars.next(), chars.next()) {
+                (Some(color), None) if color.is_ascii_uppercase() => (abbreviation, Some(color)),
+                _ => return Err(invalid()),
+            }
+        }
+        None => (cell.text.as_str(), None),
+    };
+
+    let stitch = abbreviation.parse::<Stitch>().map_err(|_| invalid())?;
+    Ok((stitch, color))
+}
+
+/// Writes a cell, quoting it if it holds the delimiter or a quote.
+fn write_cell(text: &str, delimiter: char) -> String {
+    if text.contains(delimiter) || text.contains('"') {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+impl Pattern {
+    /// Create a new Pattern from a chart saved from a spreadsheet.
+    ///
+    /// Line numbers in errors are lines of the file.  A wide stitch that runs past the end of its line gives a
+    /// `SplitsStitch` error.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - Where to read the chart from
+    /// * `delimiter` - What separates the cells, `,` for CSV or `\t` for TSV
+    /// * `options` - The options for the pattern
+    ///
+    pub fn from_csv<R: Read>(reader: R, delimiter: char, options: Options) -> Result<Pattern, ParseError> {
+        let reader = BufReader::new(reader);
+
+        let mut lines = Vec::new();
+        let mut colors = Vec::new();
+        let mut line_numbers = Vec::new();
+        for (line_number, line) in (1..).zip(reader.lines()) {
+            let line = match line {
+                Ok(line) => line,
+                Err(error) => return Err(ParseError::new(ParseErrorType::UnableToReadFromReader(Box::new(error)), line_number)),
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let cells = split_cells(&line, delimiter);
+            let mut line_stitches = VecDeque::with_capacity(cells.len());
+            let mut line_colors = VecDeque::with_capacity(cells.len());
+            let mut index = 0;
+            while index < cells.len() {
+                if cells[index].text.is_empty() {
+                    line_stitches.push_back(Stitch::NoStitch);
+                    line_colors.push_back(None);
+                    index += 1;
+                    continue;
+                }
+
+                let (stitch, color) = read_cell(&cells[index], line_number)?;
+                if index + stitch.width() > cells.len() {
+                    return Err(ParseError::new(ParseErrorType::SplitsStitch(cells.len()), line_number));
+                }
+                for covered in &cells[index + 1..index + stitch.width()] {
+                    if !covered.text.is_empty() && read_cell(covered, line_number)? != (stitch, color) {
+                        return Err(ParseError::new(ParseErrorType::InvalidSyntaxRange(covered.start, covered.end), line_number));
+                    }
+                }
+
+                line_stitches.push_back(stitch);
+                line_colors.push_back(color);
+                index += stitch.width();
+            }
+
+            lines.push(line_stitches);
+            colors.push(line_colors);
+            line_numbers.push(line_number);
+        }
+
+        // The bottom line is the first row.
+        lines.reverse();
+        colors.reverse();
+        line_numbers.reverse();
+
+        Pattern::from_parts(lines, colors, options).map_err(|parse_error| match *parse_error.error_type() {
+            ParseErrorType::InvalidStitchCount(count) => ParseError::new(ParseErrorType::InvalidStitchCount(count), line_numbers[parse_error.line_number()]),
+            _ => parse_error,
+        })
+    }
+
+    /// Writes the padded chart so it can be opened in a spreadsheet.
+    ///
+    /// The last row is written first, so the chart reads bottom up the same as `from_csv`.  A wide stitch
+    /// is written in its first cell and the cells it covers are left empty, and `nostitch` is written as
+    /// an empty cell.
+    ///
+    /// # Arguments
+    ///
+    /// * `delimiter` - What separates the cells, `,` for CSV or `\t` for TSV
+    ///
+    pub fn to_csv(&self, delimiter: char) -> String {
+        let mut out = String::new();
+        for (line, colors) in self.lines().iter().zip(self.colors()).rev() {
+            let mut cells: Vec<String> = Vec::with_capacity(self.pattern_width());
+            for (stitch, color) in line.iter().zip(colors) {
+                let text = match (stitch, color) {
+                    (Stitch::NoStitch, _) => String::new(),
+                    (stitch, Some(color)) => format!("{}:{}", stitch, color),
+                    (stitch, None) => stitch.to_string(),
+                };
+                cells.push(write_cell(&text, delimiter));
+                cells.extend(std::iter::repeat_n(String::new(), stitch.width() - 1));
+            }
+            out.push_str(&cells.join(&delimiter.to_string()));
+            out.push('\n');
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Stitch::*;
+
+    #[test]
+    fn read_bottom_up() {
+        let input = "p,2lcf,2lcf,,,p\nk,\"sl kwise\",ssk:A,k2tog,k,k\n";
+        let pattern = Pattern::from_csv(input.as_bytes(), ',', Options::default()).unwrap();
+
+        assert_eq!(pattern.lines()[0], VecDeque::from(vec![K, SlKwise, Ssk, K2Tog, K, K]));
+        assert_eq!(pattern.lines()[1], VecDeque::from(vec![P, Lcf2, P]));
+        assert_eq!(pattern.colors()[0][2], Some('A'));
+    }
+
+    #[test]
+    fn round_trip() {
+        let pattern = Pattern::new(&b"k, p:B x2, k\nssk, 1rcb, yo\nk2tog, k2tog"[..]).unwrap();
+        let tsv = pattern.to_csv('\t');
+        assert_eq!(tsv, "\tk2tog\tk2tog\t\nssk\t1rcb\t\tyo\nk\tp:B\tp:B\tk\n");
+
+        let read = Pattern::from_csv(tsv.as_bytes(), '\t', Options::default()).unwrap();
+        assert_eq!(read.lines(), pattern.lines());
+        assert_eq!(read.colors(), pattern.colors());
+    }
+
+    #[test]
+    fn errors() {
+        if let Err(parse_error) = Pattern::from_csv(&b"k,k\nk,knit,k"[..], ',', Options::default()) {
+            assert_eq!(parse_error.line_number(), 2);
+            if let ParseErrorType::InvalidSyntaxRange(start, end) = *parse_error.error_type() {
+                assert_eq!(start, 2);
+                assert_eq!(end, 5);
+            } else {
+                panic!("Wrong error type returned");
+            }
+        } else {
+            panic!("Should not have been valid");
+        }
+
+        if let Err(parse_error) = Pattern::from_csv(&b"k,k,k\nk,k"[..], ',', Options::default()) {
+            assert_eq!(parse_error.line_number(), 2);
+            if let ParseErrorType::InvalidStitchCount(count) = *parse_error.error_type() {
+                assert_eq!(count, 2);
+            } else {
+                panic!("Wrong error type returned");
+            }
+        } else {
+            panic!("Should not have been valid");
+        }
+
+        assert!(Pattern::from_csv(&b"k,2lcf,,"[..], ',', Options::default()).is_err());
+        assert!(Pattern::from_csv(&b"1lcf,k"[..], ',', Options::default()).is_err());
+    }
+}
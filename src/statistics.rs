@@ -0,0 +1,201 @@
+//! Stitch statistics and yarn estimates for a pattern.
+
+use std::collections::HashMap;
+
+use crate::Pattern;
+use crate::Stitch;
+
+/// How many meters are in a yard.
+const METERS_PER_YARD: f64 = 0.9144;
+
+/// Counts of what is worked in a pattern.
+#[derive(Debug)]
+pub struct Statistics {
+    counts: HashMap<Stitch, usize>,
+    total_stitches: usize,
+    stitches_per_row: Vec<usize>,
+}
+
+impl Statistics {
+    /// Returns how many times each stitch is used, `NoStitch` is not counted.
+    pub fn counts(&self) -> &HashMap<Stitch, usize> {
+        &self.counts
+    }
+
+    /// Returns how many times the given stitch is used.
+    pub fn count(&self, stitch: Stitch) -> usize {
+        self.counts.get(&stitch).copied().unwrap_or(0)
+    }
+
+    /// Returns the total number of stitches worked, a cable works as many stitches as it is wide.
+    pub fn total_stitches(&self) -> usize {
+        self.total_stitches
+    }
+
+    /// Returns the total number of rows.
+    pub fn total_rows(&self) -> usize {
+        self.stitches_per_row.len()
+    }
+
+    /// Returns how many stitches are worked on each row.
+    pub fn stitches_per_row(&self) -> &Vec<usize> {
+        &self.stitches_per_row
+    }
+}
+
+/// How much yarn the different stitches use.
+///
+/// A plain stitch uses `loop_factor` times the width plus height of a stitch at the pattern's gauge.
+/// Each stitch then uses that times its factor, stitches without a factor use `1.0`.
+#[derive(Debug, Clone)]
+pub struct YarnFactors {
+    /// How much yarn one loop uses compared to the size of a stitch.
+    pub loop_factor: f64,
+
+    /// How much more (or less) yarn each stitch uses compared to a plain stitch.
+    pub stitch_factors: HashMap<Stitch, f64>,
+}
+
+impl Default for YarnFactors {
+    fn default() -> Self {
+        let mut stitch_factors = HashMap::new();
+        for cable in [
+            Stitch::Lcf1,
+            Stitch::Rcb1,
+            Stitch::Lcf2,
+            Stitch::Rcb2,
+            Stitch::Lcf3,
+            Stitch::Rcb3,
+            Stitch::Lcf4,
+            Stitch::Rcb4,
+        ] {
+            stitch_factors.insert(cable, 1.25);
+        }
+        stitch_factors.insert(Stitch::Bobble, 4.0);
+        stitch_factors.insert(Stitch::Bo, 1.5);
+        stitch_factors.insert(Stitch::Kfb, 1.5);
+        stitch_factors.insert(Stitch::Kbf, 1.5);
+        stitch_factors.insert(Stitch::Pfb, 1.5);
+        stitch_factors.insert(Stitch::Pbf, 1.5);
+        stitch_factors.insert(Stitch::Yo, 0.75);
+        stitch_factors.insert(Stitch::SlKwise, 0.25);
+        stitch_factors.insert(Stitch::SlPwise, 0.25);
+
+        YarnFactors {
+            loop_factor: 2.0,
+            stitch_factors,
+        }
+    }
+}
+
+impl YarnFactors {
+    /// Returns the factor for the given stitch.
+    pub fn factor(&self, stitch: Stitch) -> f64 {
+        self.stitch_factors.get(&stitch).copied().unwrap_or(1.0)
+    }
+}
+
+/// An estimate of how much yarn a pattern uses.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct YarnEstimate {
+    /// The yarn used in meters.
+    pub meters: f64,
+}
+
+impl YarnEstimate {
+    /// Returns the yarn used in yards.
+    pub fn yards(&self) -> f64 {
+        self.meters / METERS_PER_YARD
+    }
+
+    /// Returns how many whole skeins are needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `meters_per_skein` - How many meters are in one skein
+    pub fn skeins(&self, meters_per_skein: f64) -> usize {
+        (self.meters / meters_per_skein).ceil() as usize
+    }
+}
+
+impl Pattern {
+    /// Counts the stitches and rows worked in the pattern.
+    pub fn statistics(&self) -> Statistics {
+        let mut counts = HashMap::new();
+        let mut total_stitches = 0;
+        let mut stitches_per_row = Vec::with_capacity(self.lines().len());
+
+        for line in self.lines() {
+            let mut row_stitches = 0;
+            for stitch in line.iter().filter(|stitch| **stitch != Stitch::NoStitch) {
+                *counts.entry(*stitch).or_insert(0) += 1;
+                row_stitches += stitch.width();
+            }
+
+            total_stitches += row_stitches;
+            stitches_per_row.push(row_stitches);
+        }
+
+        Statistics {
+            counts,
+            total_stitches,
+            stitches_per_row,
+        }
+    }
+
+    /// Estimates how much yarn the pattern uses, if a gauge was given.
+    ///
+    /// # Arguments
+    ///
+    /// * `factors` - How much yarn each stitch uses
+    pub fn estimate_yarn(&self, factors: &YarnFactors) -> Option<YarnEstimate> {
+        let gauge = self.options().gauge?;
+        let loop_cm = factors.loop_factor * (gauge.stitch_width_cm() + gauge.row_height_cm());
+
+        let mut total_cm = 0.0;
+        for (stitch, count) in self.statistics().counts() {
+            total_cm += loop_cm * factors.factor(*stitch) * stitch.width() as f64 * *count as f64;
+        }
+
+        Some(YarnEstimate { meters: total_cm / 100.0 })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn counts() {
+        let input = b"k x4\n2lcf\nk, p2tog";
+        let statistics = Pattern::new(&input[..]).unwrap().statistics();
+
+        assert_eq!(statistics.count(Stitch::K), 5);
+        assert_eq!(statistics.count(Stitch::Lcf2), 1);
+        assert_eq!(statistics.count(Stitch::NoStitch), 0);
+        assert_eq!(statistics.total_rows(), 3);
+        assert_eq!(statistics.stitches_per_row(), &vec![4, 4, 2]);
+        assert_eq!(statistics.total_stitches(), 10);
+    }
+
+    #[test]
+    fn yarn_estimate() {
+        let input = b"## gauge=20x20/10cm\nk x10\nk x10";
+        let pattern = Pattern::new(&input[..]).unwrap();
+        let estimate = pattern.estimate_yarn(&YarnFactors::default()).unwrap();
+
+        // 20 stitches, each 2 * (0.5 + 0.5) cm
+        assert!((estimate.meters - 0.4).abs() < 1e-9);
+        assert_eq!(estimate.skeins(0.15), 3);
+    }
+
+    #[test]
+    fn cables_use_more() {
+        let plain = Pattern::new(&b"## gauge=20x20/10cm\nk x4"[..]).unwrap();
+        let cabled = Pattern::new(&b"## gauge=20x20/10cm\n2lcf"[..]).unwrap();
+        let factors = YarnFactors::default();
+
+        assert!(cabled.estimate_yarn(&factors).unwrap().meters > plain.estimate_yarn(&factors).unwrap().meters);
+        assert!(Pattern::new(&b"k x4"[..]).unwrap().estimate_yarn(&factors).is_none());
+    }
+}
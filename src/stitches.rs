@@ -1,5 +1,10 @@
 //! Holds the definition and details for the supported stitches
 
+use std::str::FromStr;
+
+use crate::ParseError;
+use crate::ParseErrorType;
+
 /// The supported stitches
 #[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
 pub enum Stitch {
@@ -46,6 +51,41 @@ pub enum Stitch {
 }
 
 impl Stitch {
+    /// Every supported stitch, in the order they are declared.
+    pub const ALL: [Stitch; 31] = [
+        Stitch::K,
+        Stitch::P,
+        Stitch::K2Tog,
+        Stitch::P2Tog,
+        Stitch::Ssk,
+        Stitch::Ssp,
+        Stitch::SlKwise,
+        Stitch::SlPwise,
+        Stitch::Yo,
+        Stitch::Bo,
+        Stitch::Mr,
+        Stitch::Ml,
+        Stitch::MKwise,
+        Stitch::MPwise,
+        Stitch::Kfb,
+        Stitch::Kbf,
+        Stitch::Pfb,
+        Stitch::Pbf,
+        Stitch::Ktbl,
+        Stitch::Ptbl,
+        Stitch::NoStitch,
+        Stitch::Bobble,
+        Stitch::Bead,
+        Stitch::Lcf1,
+        Stitch::Rcb1,
+        Stitch::Lcf2,
+        Stitch::Rcb2,
+        Stitch::Lcf3,
+        Stitch::Rcb3,
+        Stitch::Lcf4,
+        Stitch::Rcb4,
+    ];
+
     /// Returns the width of the stitch.
     ///
     /// This is useful when trying to layout the stitches next to each other.
@@ -88,4 +128,257 @@ impl Stitch {
             Stitch::Rcb4 => 8,
         }
     }
+
+    /// Returns the stitch to work on the wrong side to get this stitch on the right side.
+    ///
+    /// Charts show how the right side looks, so a `k` on a wrong side row is worked as a `p`.
+    pub fn wrong_side(&self) -> Stitch {
+        match self {
+            Stitch::K => Stitch::P,
+            Stitch::P => Stitch::K,
+            Stitch::K2Tog => Stitch::P2Tog,
+            Stitch::P2Tog => Stitch::K2Tog,
+            Stitch::Ssk => Stitch::Ssp,
+            Stitch::Ssp => Stitch::Ssk,
+            Stitch::Ktbl => Stitch::Ptbl,
+            Stitch::Ptbl => Stitch::Ktbl,
+            Stitch::Kfb => Stitch::Pfb,
+            Stitch::Pfb => Stitch::Kfb,
+            Stitch::Kbf => Stitch::Pbf,
+            Stitch::Pbf => Stitch::Kbf,
+            Stitch::MKwise => Stitch::MPwise,
+            Stitch::MPwise => Stitch::MKwise,
+
+            stitch => *stitch,
+        }
+    }
+
+    /// Returns the stitch that leans or crosses the other way.
+    ///
+    /// This is used when mirroring a pattern left to right so the fabric mirrors too.
+    pub fn mirrored(&self) -> Stitch {
+        match self {
+            Stitch::K2Tog => Stitch::Ssk,
+            Stitch::Ssk => Stitch::K2Tog,
+            Stitch::P2Tog => Stitch::Ssp,
+            Stitch::Ssp => Stitch::P2Tog,
+            Stitch::Ml => Stitch::Mr,
+            Stitch::Mr => Stitch::Ml,
+
+            Stitch::Lcf1 => Stitch::Rcb1,
+            Stitch::Rcb1 => Stitch::Lcf1,
+            Stitch::Lcf2 => Stitch::Rcb2,
+            Stitch::Rcb2 => Stitch::Lcf2,
+            Stitch::Lcf3 => Stitch::Rcb3,
+            Stitch::Rcb3 => Stitch::Lcf3,
+            Stitch::Lcf4 => Stitch::Rcb4,
+            Stitch::Rcb4 => Stitch::Lcf4,
+
+            stitch => *stitch,
+        }
+    }
+
+    /// Returns how many stitches from the row below are worked by this stitch.
+    pub fn consumes(&self) -> usize {
+        match self {
+            Stitch::NoStitch => 0,
+            Stitch::Yo | Stitch::Ml | Stitch::Mr | Stitch::MKwise | Stitch::MPwise => 0,
+            Stitch::K2Tog | Stitch::P2Tog | Stitch::Ssk | Stitch::Ssp => 2,
+            stitch => stitch.width(),
+        }
+    }
+
+    /// Returns how many stitches this stitch leaves on the needle.
+    pub fn produces(&self) -> usize {
+        match self {
+            Stitch::NoStitch | Stitch::Bo => 0,
+            Stitch::Kfb | Stitch::Kbf | Stitch::Pfb | Stitch::Pbf => 2,
+            stitch => stitch.width(),
+        }
+    }
+
+    /// Returns if the stitch is worked as a purl.
+    pub fn is_purl(&self) -> bool {
+        matches!(
+            self,
+            Stitch::P | Stitch::P2Tog | Stitch::Ssp | Stitch::Ptbl | Stitch::Pfb | Stitch::Pbf | Stitch::MPwise | Stitch::SlPwise
+        )
+    }
+
+    /// Returns if the stitch works two stitches together into one.
+    pub fn is_decrease(&self) -> bool {
+        matches!(self, Stitch::K2Tog | Stitch::P2Tog | Stitch::Ssk | Stitch::Ssp)
+    }
+
+    /// Returns what the stitch means in words, such as `knit two stitches together`.
+    pub fn description(&self) -> String {
+        let description = match self {
+            Stitch::K => "knit",
+            Stitch::P => "purl",
+            Stitch::K2Tog => "knit two stitches together",
+            Stitch::P2Tog => "purl two stitches together",
+            Stitch::Ssk => "slip two stitches knitwise one at a time, then knit them together through the back loops",
+            Stitch::Ssp => "slip two stitches knitwise one at a time, then purl them together through the back loops",
+            Stitch::SlKwise => "slip one stitch knitwise",
+            Stitch::SlPwise => "slip one stitch purlwise",
+            Stitch::Yo => "yarn over",
+            Stitch::Bo => "bind off one stitch",
+            Stitch::Mr => "make one right leaning stitch",
+            Stitch::Ml => "make one left leaning stitch",
+            Stitch::MKwise => "make one stitch knitwise",
+            Stitch::MPwise => "make one stitch purlwise",
+            Stitch::Kfb => "knit into the front and then the back of the stitch",
+            Stitch::Kbf => "knit into the back and then the front of the stitch",
+            Stitch::Pfb => "purl into the front and then the back of the stitch",
+            Stitch::Pbf => "purl into the back and then the front of the stitch",
+            Stitch::Ktbl => "knit through the back loop",
+            Stitch::Ptbl => "purl through the back loop",
+            Stitch::NoStitch => "no stitch, only there to line up the chart",
+            Stitch::Bobble => "make a bobble",
+            Stitch::Bead => "place a bead",
+
+            // Cables hold half their stitches on a cable needle
+            Stitch::Lcf1 | Stitch::Lcf2 | Stitch::Lcf3 | Stitch::Lcf4 | Stitch::Rcb1 | Stitch::Rcb2 | Stitch::Rcb3 | Stitch::Rcb4 => {
+                let half = self.width() / 2;
+                let held = if half == 1 { "1 stitch".to_string() } else { format!("{} stitches", half) };
+                let side = if matches!(self, Stitch::Lcf1 | Stitch::Lcf2 | Stitch::Lcf3 | Stitch::Lcf4) {
+                    "front"
+                } else {
+                    "back"
+                };
+                return format!(
+                    "slip {} to a cable needle and hold in {}, knit {}, then knit {} from the cable needle",
+                    held, side, half, half
+                );
+            }
+        };
+        description.to_string()
+    }
+
+    /// Returns how the stitch is drawn on a chart, one character for each column it covers.
+    pub fn symbol(&self) -> String {
+        let symbol = match self {
+            Stitch::K => '|',
+            Stitch::P => '-',
+            Stitch::K2Tog => '/',
+            Stitch::P2Tog => '⟋',
+            Stitch::Ssk => '\\',
+            Stitch::Ssp => '⟍',
+            Stitch::SlKwise => 'V',
+            Stitch::SlPwise => 'v',
+            Stitch::Yo => 'O',
+            Stitch::Bo => 'x',
+            Stitch::Mr => 'R',
+            Stitch::Ml => 'L',
+            Stitch::MKwise => 'M',
+            Stitch::MPwise => 'm',
+            Stitch::Kfb => 'Y',
+            Stitch::Kbf => 'y',
+            Stitch::Pfb => 'W',
+            Stitch::Pbf => 'w',
+            Stitch::Ktbl => 'Q',
+            Stitch::Ptbl => 'q',
+            Stitch::NoStitch => ' ',
+            Stitch::Bobble => '@',
+            Stitch::Bead => '*',
+
+            // Cables are drawn across all their columns
+            Stitch::Lcf1 | Stitch::Lcf2 | Stitch::Lcf3 | Stitch::Lcf4 => return format!("<{}<", "=".repeat(self.width() - 2)),
+            Stitch::Rcb1 | Stitch::Rcb2 | Stitch::Rcb3 | Stitch::Rcb4 => return format!(">{}>", "=".repeat(self.width() - 2)),
+        };
+        symbol.to_string()
+    }
+}
+
+impl std::fmt::Display for Stitch {
+    /// Writes the stitch the same way it's written in a pattern, such as `k2tog` or `sl kwise`.
+    fn fmt(&self, out: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let abbreviation = match self {
+            Stitch::K => "k",
+            Stitch::P => "p",
+            Stitch::K2Tog => "k2tog",
+            Stitch::P2Tog => "p2tog",
+            Stitch::Ssk => "ssk",
+            Stitch::Ssp => "ssp",
+            Stitch::SlKwise => "sl kwise",
+            Stitch::SlPwise => "sl pwise",
+            Stitch::Yo => "yo",
+            Stitch::Bo => "bo",
+            Stitch::Mr => "mr",
+            Stitch::Ml => "ml",
+            Stitch::MKwise => "m kwise",
+            Stitch::MPwise => "m pwise",
+            Stitch::Kfb => "kfb",
+            Stitch::Kbf => "kbf",
+            Stitch::Pfb => "pfb",
+            Stitch::Pbf => "pbf",
+            Stitch::Ktbl => "ktbl",
+            Stitch::Ptbl => "ptbl",
+            Stitch::NoStitch => "nostitch",
+            Stitch::Bobble => "bobble",
+            Stitch::Bead => "bead",
+
+            Stitch::Lcf1 => "1lcf",
+            Stitch::Rcb1 => "1rcb",
+
+            Stitch::Lcf2 => "2lcf",
+            Stitch::Rcb2 => "2rcb",
+
+            Stitch::Lcf3 => "3lcf",
+            Stitch::Rcb3 => "3rcb",
+
+            Stitch::Lcf4 => "4lcf",
+            Stitch::Rcb4 => "4rcb",
+        };
+
+        write!(out, "{}", abbreviation)
+    }
+}
+
+impl FromStr for Stitch {
+    type Err = ParseError;
+
+    /// Reads a single stitch written the same way as in a pattern, such as `k2tog` or `sl kwise`.
+    fn from_str(abbreviation: &str) -> Result<Stitch, ParseError> {
+        let trimmed = abbreviation.trim();
+        match Stitch::ALL.iter().find(|stitch| stitch.to_string() == trimmed) {
+            Some(stitch) => Ok(*stitch),
+            None => Err(ParseError::new(ParseErrorType::InvalidSyntaxRange(0, abbreviation.len().saturating_sub(1)), 0)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn abbreviations_read_back() {
+        for stitch in Stitch::ALL {
+            assert_eq!(stitch.to_string().parse::<Stitch>().unwrap(), stitch);
+        }
+        assert!("k x2".parse::<Stitch>().is_err());
+    }
+
+    #[test]
+    fn wrong_side_and_mirror() {
+        for stitch in Stitch::ALL {
+            assert_eq!(stitch.wrong_side().wrong_side(), stitch);
+            assert_eq!(stitch.mirrored().mirrored(), stitch);
+            assert_eq!(stitch.mirrored().width(), stitch.width());
+            assert_eq!(stitch.symbol().chars().count(), stitch.width());
+        }
+    }
+
+    #[test]
+    fn stitch_counts() {
+        assert_eq!((Stitch::K2Tog.consumes(), Stitch::K2Tog.produces()), (2, 1));
+        assert_eq!((Stitch::Yo.consumes(), Stitch::Yo.produces()), (0, 1));
+        assert_eq!((Stitch::Kfb.consumes(), Stitch::Kfb.produces()), (1, 2));
+        assert_eq!((Stitch::Rcb2.consumes(), Stitch::Rcb2.produces()), (4, 4));
+        assert_eq!(
+            Stitch::Lcf1.description(),
+            "slip 1 stitch to a cable needle and hold in front, knit 1, then knit 1 from the cable needle"
+        );
+    }
 }
@@ -0,0 +1,242 @@
+//! Builds new patterns out of existing ones.
+
+use std::collections::VecDeque;
+use std::iter;
+use std::ops::Range;
+
+use crate::grid::splits_stitch;
+use crate::ParseError;
+use crate::ParseErrorType;
+use crate::Pattern;
+use crate::Stitch;
+
+/// Returns the row repeated `across` times.
+fn tile_row<T: Copy>(row: &VecDeque<T>, across: usize) -> VecDeque<T> {
+    iter::repeat_n(row, across).flatten().copied().collect()
+}
+
+/// Drops `padding` entries from both ends of a row, they are `nostitch` padding that `from_parts` adds back.
+fn unpad<T: Copy>(row: VecDeque<T>, padding: usize) -> VecDeque<T> {
+    row.range(padding..row.len() - padding).copied().collect()
+}
+
+impl Pattern {
+    /// Returns the pattern mirrored left to right.
+    ///
+    /// Each stitch is swapped for the one leaning or crossing the other way, so `k2tog` becomes `ssk` and `1lcf` becomes `1rcb`.
+    pub fn mirror(&self) -> Pattern {
+        let (lines, colors) = (0..self.lines().len())
+            .map(|row| {
+                let (line, colors) = self.unpadded_row(row);
+                (line.iter().rev().map(Stitch::mirrored).collect(), colors.into_iter().rev().collect())
+            })
+            .unzip();
+
+        // This unwrap should be fine, the rows are all still the same width.
+        self.with_rows(lines, colors).unwrap()
+    }
+
+    /// Returns the pattern upside down, the last row becomes the first.
+    pub fn flip(&self) -> Pattern {
+        let (lines, colors) = (0..self.lines().len()).rev().map(|row| self.unpadded_row(row)).unzip();
+
+        // This unwrap should be fine, the rows are all still the same width.
+        self.with_rows(lines, colors).unwrap()
+    }
+
+    /// Returns the pattern repeated `across` times side by side and `up` times on top of itself.
+    ///
+    /// # Arguments
+    ///
+    /// * `across` - How many times to repeat each row
+    /// * `up` - How many times to repeat all the rows
+    ///
+    pub fn tile(&self, across: usize, up: usize) -> Pattern {
+        // The padding between two tiles is kept as `nostitch`, only the padding at the ends is dropped.
+        let rows = iter::repeat_n(0..self.lines().len(), up).flatten();
+        let (lines, colors) = rows
+            .map(|row| {
+                let padding = self.padding(row);
+                (
+                    unpad(tile_row(&self.lines()[row], across), padding),
+                    unpad(tile_row(&self.colors()[row], across), padding),
+                )
+            })
+            .unzip();
+
+        // This unwrap should be fine, the rows are all still the same width.
+        self.with_rows(lines, colors).unwrap()
+    }
+
+    /// Returns the other pattern worked on top of this one.
+    ///
+    /// The narrower pattern is padded, so the widths must differ by an even number.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The pattern to work after this one
+    ///
+    pub fn stack(&self, other: &Pattern) -> Result<Pattern, ParseError> {
+        let rows = (0..self.lines().len()).map(|row| self.unpadded_row(row));
+        let (lines, colors) = rows.chain((0..other.lines().len()).map(|row| other.unpadded_row(row))).unzip();
+
+        self.with_rows(lines, colors)
+    }
+
+    /// Returns this pattern and the other side by side, this one on the left.
+    ///
+    /// If one pattern has fewer rows it's topped up with rows of `nostitch`.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The pattern to place on the right
+    /// * `separator` - A stitch worked between the two patterns on every row, if any
+    ///
+    pub fn beside(&self, other: &Pattern, separator: Option<Stitch>) -> Pattern {
+        let rows = self.lines().len().max(other.lines().len());
+        let blank = |pattern: &Pattern| {
+            (
+                VecDeque::from(vec![Stitch::NoStitch; pattern.pattern_width()]),
+                VecDeque::from(vec![None; pattern.pattern_width()]),
+            )
+        };
+
+        let mut lines = Vec::with_capacity(rows);
+        let mut colors = Vec::with_capacity(rows);
+        for row in 0..rows {
+            // A missing row is all padding.
+            let (left, left_colors, left_padding) = match self.lines().get(row) {
+                Some(line) => (line.clone(), self.colors()[row].clone(), self.padding(row)),
+                None => {
+                    let (line, colors) = blank(self);
+                    (line, colors, self.pattern_width())
+                }
+            };
+            let (right, right_colors, right_padding) = match other.lines().get(row) {
+                Some(line) => (line.clone(), other.colors()[row].clone(), other.padding(row)),
+                None => {
+                    let (line, colors) = blank(other);
+                    (line, colors, other.pattern_width())
+                }
+            };
+
+            // Only as much padding as both ends have is dropped, the rest is kept as `nostitch` to line the patterns up.
+            let padding = left_padding.min(right_padding);
+            lines.push(unpad(left.into_iter().chain(separator).chain(right).collect(), padding));
+            colors.push(unpad(
+                left_colors.into_iter().chain(separator.map(|_| None)).chain(right_colors).collect(),
+                padding,
+            ));
+        }
+
+        // This unwrap should be fine, every row is made of the same widths.
+        self.with_rows(lines, colors).unwrap()
+    }
+
+    /// Returns the part of the pattern inside the given rows and columns.
+    ///
+    /// # Arguments
+    ///
+    /// * `rows` - Which rows to keep
+    /// * `columns` - Which columns to keep, these can't cut through a wide stitch
+    ///
+    pub fn crop(&self, rows: Range<usize>, columns: Range<usize>) -> Result<Pattern, ParseError> {
+        if rows.start > rows.end || rows.end > self.lines().len() {
+            return Err(ParseError::new(ParseErrorType::RowOutOfRange(rows.end), rows.end));
+        }
+        if columns.start > columns.end || columns.end > self.pattern_width() {
+            return Err(ParseError::new(ParseErrorType::ColumnOutOfRange(columns.end), rows.start));
+        }
+
+        let mut cropped = Vec::with_capacity(rows.len());
+        for (row, cells) in self.cells().into_iter().enumerate().skip(rows.start).take(rows.len()) {
+            for column in [columns.start, columns.end] {
+                if splits_stitch(&cells, column) {
+                    return Err(ParseError::new(ParseErrorType::SplitsStitch(column), row));
+                }
+            }
+
+            // Drop whatever padding the crop kept on both ends, it's added back when the rows are padded again.
+            let width = columns.len();
+            let left = self.padding(row).saturating_sub(columns.start).min(width);
+            let right = columns.end.saturating_sub(self.pattern_width() - self.padding(row)).min(width);
+            let padding = left.min(right).min(width / 2);
+            cropped.push(cells[columns.start + padding..columns.end - padding].to_vec());
+        }
+
+        self.with_cells(&cropped)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Stitch::*;
+
+    fn pattern(input: &str) -> Pattern {
+        Pattern::new(input.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn mirror() {
+        let mirrored = pattern("k, k2tog, 1lcf, ml").mirror();
+        assert_eq!(mirrored.lines()[0], VecDeque::from(vec![Mr, Rcb1, Ssk, K]));
+    }
+
+    #[test]
+    fn padding_kept() {
+        let source = pattern("k x5\nk x3\nnostitch, p, nostitch");
+        let mirrored = source.mirror();
+        assert!(source.diff(&mirrored).is_empty());
+        assert_eq!(mirrored.to_text(), "k x5\nk x3\nnostitch, p, nostitch\n");
+        assert!(source.diff(&source.flip().flip()).is_empty());
+        assert!(source.diff(&pattern("k x3").stack(&source).unwrap().crop(1..4, 0..5).unwrap()).is_empty());
+
+        let tiled = pattern("k x4\nk x2").tile(2, 1);
+        assert_eq!(tiled.to_text(), "k x8\nk x2, nostitch x2, k x2\n");
+
+        let beside = pattern("k x4\nk x2").beside(&pattern("p x2\np x2"), None);
+        assert_eq!(beside.to_text(), "k x4, p x2\nnostitch, k x2, nostitch, p x2\n");
+    }
+
+    #[test]
+    fn flip_and_tile() {
+        let flipped = pattern("k, p\np, p").flip();
+        assert_eq!(flipped.lines()[0], VecDeque::from(vec![P, P]));
+
+        let tiled = pattern("k:A, p").tile(3, 2);
+        assert_eq!(tiled.lines().len(), 2);
+        assert_eq!(tiled.pattern_width(), 6);
+        assert_eq!(tiled.colors()[1], VecDeque::from(vec![Some('A'), None, Some('A'), None, Some('A'), None]));
+    }
+
+    #[test]
+    fn stack_and_beside() {
+        let stacked = pattern("k x2").stack(&pattern("p x4")).unwrap();
+        assert_eq!(stacked.lines()[0], VecDeque::from(vec![NoStitch, K, K, NoStitch]));
+        assert!(pattern("k x2").stack(&pattern("p x3")).is_err());
+
+        let beside = pattern("k x2\nk x2").beside(&pattern("1lcf"), Some(P));
+        assert_eq!(beside.pattern_width(), 5);
+        assert_eq!(beside.lines()[0], VecDeque::from(vec![K, K, P, Lcf1]));
+        assert_eq!(beside.lines()[1], VecDeque::from(vec![K, K, P, NoStitch, NoStitch]));
+    }
+
+    #[test]
+    fn crop() {
+        let source = pattern("k, p, 1lcf, p\np, p, k, k, p\nk x5");
+        let cropped = source.crop(1..3, 1..4).unwrap();
+        assert_eq!(cropped.lines(), &vec![VecDeque::from(vec![P, K, K]), VecDeque::from(vec![K; 3])]);
+
+        if let Err(parse_error) = source.crop(0..2, 0..3) {
+            if let ParseErrorType::SplitsStitch(column) = *parse_error.error_type() {
+                assert_eq!(column, 3);
+            } else {
+                panic!("Wrong error type returned");
+            }
+        } else {
+            panic!("Should not have cropped through a cable");
+        }
+        assert!(source.crop(0..4, 0..2).is_err());
+    }
+}